@@ -12,7 +12,7 @@ async function loadWasm(wasmPath) {
     return instance;
 }
 
-function wasmCompress(instance, data, abi) {
+function wasmCompress(instance, data, abi, level) {
     const exports = instance.exports;
     const inLen = data.length;
     const outLen = inLen + 1024;
@@ -23,7 +23,7 @@ function wasmCompress(instance, data, abi) {
     let memory = new Uint8Array(exports.memory.buffer);
     memory.set(data, inPtr);
     
-    const written = exports[abi](inPtr, inLen, outPtr, outLen);
+    const written = exports[abi](inPtr, inLen, outPtr, outLen, level);
     
     if (written < 0) {
         exports.free_bytes(inPtr, inLen);
@@ -112,11 +112,11 @@ async function main() {
     const gzipJsTime = benchmark(() => pako.gzip(data, { level: 6 }));
     const gzipJsResult = pako.gzip(data, { level: 6 });
     
-    const gzipBaseTime = benchmark(() => wasmCompress(gzipBase, data, 'compress_gzip_level_6'));
-    const gzipBaseResult = wasmCompress(gzipBase, data, 'compress_gzip_level_6');
+    const gzipBaseTime = benchmark(() => wasmCompress(gzipBase, data, 'compress_gzip', 6));
+    const gzipBaseResult = wasmCompress(gzipBase, data, 'compress_gzip', 6);
     
-    const gzipSimdTime = benchmark(() => wasmCompress(gzipSimd, data, 'compress_gzip_level_6'));
-    const gzipSimdResult = wasmCompress(gzipSimd, data, 'compress_gzip_level_6');
+    const gzipSimdTime = benchmark(() => wasmCompress(gzipSimd, data, 'compress_gzip', 6));
+    const gzipSimdResult = wasmCompress(gzipSimd, data, 'compress_gzip', 6);
     
     console.log('| Implementation     | Time (ms) | Speed        | vs JS     | Ratio  |');
     console.log('|--------------------|-----------|--------------|-----------|--------|');
@@ -129,11 +129,11 @@ async function main() {
     console.log('  BROTLI (Level 6)');
     console.log('─'.repeat(80));
     
-    const brotliBaseTime = benchmark(() => wasmCompress(brotliBase, data, 'compress_brotli_level_6'));
-    const brotliBaseResult = wasmCompress(brotliBase, data, 'compress_brotli_level_6');
+    const brotliBaseTime = benchmark(() => wasmCompress(brotliBase, data, 'compress_brotli', 6));
+    const brotliBaseResult = wasmCompress(brotliBase, data, 'compress_brotli', 6);
     
-    const brotliSimdTime = benchmark(() => wasmCompress(brotliSimd, data, 'compress_brotli_level_6'));
-    const brotliSimdResult = wasmCompress(brotliSimd, data, 'compress_brotli_level_6');
+    const brotliSimdTime = benchmark(() => wasmCompress(brotliSimd, data, 'compress_brotli', 6));
+    const brotliSimdResult = wasmCompress(brotliSimd, data, 'compress_brotli', 6);
     
     console.log('| Implementation     | Time (ms) | Speed        | vs Base   | Ratio  |');
     console.log('|--------------------|-----------|--------------|-----------|--------|');
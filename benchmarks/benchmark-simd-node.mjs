@@ -11,7 +11,7 @@ async function loadWasm(wasmPath) {
     return instance;
 }
 
-function compress(instance, data, abi) {
+function compress(instance, data, abi, level) {
     const exports = instance.exports;
     const inLen = data.length;
     const outLen = inLen + 1024;
@@ -22,7 +22,7 @@ function compress(instance, data, abi) {
     let memory = new Uint8Array(exports.memory.buffer);
     memory.set(data, inPtr);
     
-    const written = exports[abi](inPtr, inLen, outPtr, outLen);
+    const written = exports[abi](inPtr, inLen, outPtr, outLen, level);
     
     if (written < 0) {
         exports.free_bytes(inPtr, inLen);
@@ -38,16 +38,16 @@ function compress(instance, data, abi) {
     return result;
 }
 
-function benchmark(instance, data, abi, runs = 5) {
+function benchmark(instance, data, abi, level, runs = 5) {
     // Warmup
     for (let i = 0; i < 2; i++) {
-        compress(instance, data, abi);
+        compress(instance, data, abi, level);
     }
     
     const times = [];
     for (let i = 0; i < runs; i++) {
         const start = performance.now();
-        compress(instance, data, abi);
+        compress(instance, data, abi, level);
         times.push(performance.now() - start);
     }
     
@@ -84,16 +84,16 @@ async function main() {
     console.log(`Input size: ${(data.length / 1024).toFixed(1)} KB\n`);
     
     const tests = [
-        { name: 'Brotli', level: 1, abi: 'compress_brotli_level_1', 
+        { name: 'Brotli', level: 1, abi: 'compress_brotli', 
           base: '../js/brotli/dist/wasm/brotli.base.wasm',
           simd: '../js/brotli/dist/wasm/brotli.simd.wasm' },
-        { name: 'Brotli', level: 4, abi: 'compress_brotli_level_4',
+        { name: 'Brotli', level: 4, abi: 'compress_brotli',
           base: '../js/brotli/dist/wasm/brotli.base.wasm',
           simd: '../js/brotli/dist/wasm/brotli.simd.wasm' },
-        { name: 'Gzip', level: 1, abi: 'compress_gzip_level_1',
+        { name: 'Gzip', level: 1, abi: 'compress_gzip',
           base: '../js/gzip/dist/wasm/gzip.base.wasm',
           simd: '../js/gzip/dist/wasm/gzip.simd.wasm' },
-        { name: 'Gzip', level: 6, abi: 'compress_gzip_level_6',
+        { name: 'Gzip', level: 6, abi: 'compress_gzip',
           base: '../js/gzip/dist/wasm/gzip.base.wasm',
           simd: '../js/gzip/dist/wasm/gzip.simd.wasm' },
         { name: 'LZ4 Frame', level: '-', abi: 'compress_lz4',
@@ -114,8 +114,8 @@ async function main() {
         const baseInstance = await loadWasm(basePath);
         const simdInstance = await loadWasm(simdPath);
         
-        const baseTime = benchmark(baseInstance, data, test.abi);
-        const simdTime = benchmark(simdInstance, data, test.abi);
+        const baseTime = benchmark(baseInstance, data, test.abi, test.level);
+        const simdTime = benchmark(simdInstance, data, test.abi, test.level);
         const speedup = baseTime / simdTime;
         
         const baseSpeed = (sizeMB / (baseTime / 1000)).toFixed(1);
@@ -1,8 +1,14 @@
-use codec_brotli::BrotliCompressor;
-use core_api::{CompressionOptions, Compressor, Flush};
-use std::collections::HashMap;
+use codec_brotli::{
+    BrotliCompressor, BrotliDecompressOptions, BrotliDecompressor, BrotliError, BrotliFormat,
+    BrotliMode, BrotliOptions,
+};
+use core_api::abi::{self, ErrorCode};
+use core_api::handles::{HandleKind, HandleRegistry};
+use core_api::{
+    CompressionOptions, Compressor, DecompressionOptions, Decompressor, ErrorKind, Flush,
+    HasErrorKind,
+};
 use std::sync::Mutex;
-use std::sync::LazyLock;
 
 // --- wasm-bindgen-lite bindings ---
 
@@ -20,19 +26,121 @@ pub unsafe extern "C" fn free_bytes(ptr: *mut u8, len: usize) {
     dealloc(ptr, layout);
 }
 
-// Static storage for streaming compressors
-static COMPRESSORS: LazyLock<Mutex<HashMap<u32, BrotliCompressor>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
-static mut HANDLE_COUNTER: u32 = 1;
+/// Code of the last failed call (see `core_api::abi::ErrorCode`).
+#[no_mangle]
+pub extern "C" fn last_error_code() -> i32 {
+    abi::last_error_code() as i32
+}
+
+/// Copy the UTF-8 description of the last failed call into `out_ptr`.
+/// Returns its length, or the negative size needed.
+#[no_mangle]
+pub unsafe extern "C" fn last_error_message(out_ptr: *mut u8, out_len: usize) -> isize {
+    let message = abi::last_error_message();
+    if message.len() > out_len {
+        return abi::needed_size(message.len());
+    }
+    std::ptr::copy_nonoverlapping(message.as_ptr(), out_ptr, message.len());
+    message.len() as isize
+}
+
+// Static storage for streaming compressors and decompressors
+core_api::handle_entries! {
+    enum Entry {
+        Compressor(BrotliCompressorState),
+        Decompressor(BrotliDecompressorState),
+    }
+}
+
+static HANDLES: Mutex<HandleRegistry<Entry>> = Mutex::new(HandleRegistry::new());
+
+/// Store a new object and return its handle, or 0 when every handle is taken.
+fn insert_handle<K: HandleKind<Entry>>(value: K) -> u32 {
+    HANDLES.lock().unwrap().insert(value).unwrap_or_else(|| {
+        abi::fail(ErrorCode::Failed, "Too many open handles");
+        0
+    })
+}
+
+/// Record a codec failure for `last_error_message` and return its ABI code;
+/// `fallback` is the code for io errors the codec can't classify further.
+fn codec_error(e: BrotliError, fallback: ErrorCode) -> isize {
+    let code = match e.kind() {
+        ErrorKind::Io => fallback,
+        kind => ErrorCode::from(kind),
+    };
+    abi::fail(code, e)
+}
+
+/// Queue `out` behind the output JS hasn't collected yet, failing rather
+/// than aborting when memory runs out.
+fn queue_output(pending: &mut Vec<u8>, out: &[u8]) -> Result<(), isize> {
+    if pending.try_reserve(out.len()).is_err() {
+        return Err(abi::fail(ErrorCode::OutOfMemory, "Out of memory for pending output"));
+    }
+    pending.extend_from_slice(out);
+    Ok(())
+}
+
+/// Build decompression limits from the ABI arguments; 0 means unlimited.
+fn decompression_options(max_output_size: usize, max_ratio: u32) -> DecompressionOptions {
+    DecompressionOptions {
+        max_output_size: (max_output_size != 0).then_some(max_output_size as u64),
+        max_ratio: (max_ratio != 0).then_some(max_ratio),
+    }
+}
+
+/// Map the `format` byte of the ABI onto a container: 0 = brotli, 1 = dcb.
+fn brotli_format(format: u8) -> Option<BrotliFormat> {
+    match format {
+        0 => Some(BrotliFormat::Brotli),
+        1 => Some(BrotliFormat::Dcb),
+        _ => None,
+    }
+}
+
+/// Map the `mode` byte of the ABI onto an encoder mode, numbered as brotli's:
+/// 0 = generic, 1 = text, 2 = font.
+fn brotli_mode(mode: u8) -> Option<BrotliMode> {
+    match mode {
+        0 => Some(BrotliMode::Generic),
+        1 => Some(BrotliMode::Text),
+        2 => Some(BrotliMode::Font),
+        _ => None,
+    }
+}
 
-fn next_handle() -> u32 {
-    unsafe {
-        let handle = HANDLE_COUNTER;
-        HANDLE_COUNTER += 1;
-        handle
+/// Read an optional byte field of the ABI; a null pointer means absent.
+unsafe fn optional_bytes(ptr: *const u8, len: usize) -> Option<Vec<u8>> {
+    if ptr.is_null() {
+        None
+    } else if len == 0 {
+        Some(Vec::new())
+    } else {
+        Some(std::slice::from_raw_parts(ptr, len).to_vec())
     }
 }
 
-unsafe fn compress_brotli_raw(
+/// Brotli decode settings from the ABI; a null `dict_ptr` means no dictionary.
+/// Records the error for an unknown format.
+unsafe fn decompress_options(
+    format: u8,
+    dict_ptr: *const u8,
+    dict_len: usize,
+) -> Option<BrotliDecompressOptions> {
+    let Some(format) = brotli_format(format) else {
+        abi::fail(ErrorCode::Failed, format_args!("Unknown brotli format {}", format));
+        return None;
+    };
+    Some(BrotliDecompressOptions {
+        format,
+        dictionary: optional_bytes(dict_ptr, dict_len),
+    })
+}
+
+/// One-shot compression at any brotli quality (0-11).
+#[no_mangle]
+pub unsafe extern "C" fn compress_brotli(
     in_ptr: *const u8,
     in_len: usize,
     out_ptr: *mut u8,
@@ -48,52 +156,80 @@ unsafe fn compress_brotli_raw(
     match <BrotliCompressor as Compressor>::compress_all(input, opts) {
         Ok(out) => {
             if out.len() > out_len {
-                return -(out.len() as isize);
+                return abi::needed_size(out.len());
             }
             std::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len());
             out.len() as isize
         }
-        Err(_) => -1,
+        Err(e) => codec_error(e, ErrorCode::Failed),
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn compress_brotli_level_1(p: *const u8, l: usize, o: *mut u8, ol: usize) -> isize {
-    compress_brotli_raw(p, l, o, ol, 1)
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn compress_brotli_level_4(p: *const u8, l: usize, o: *mut u8, ol: usize) -> isize {
-    compress_brotli_raw(p, l, o, ol, 4)
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn compress_brotli_level_6(p: *const u8, l: usize, o: *mut u8, ol: usize) -> isize {
-    compress_brotli_raw(p, l, o, ol, 6)
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn compress_brotli_level_9(p: *const u8, l: usize, o: *mut u8, ol: usize) -> isize {
-    compress_brotli_raw(p, l, o, ol, 9)
-}
-
 // ============================================================================
 // Streaming Compression API
 // ============================================================================
 
+struct BrotliCompressorState {
+    compressor: BrotliCompressor,
+    // Compressed bytes that didn't fit the caller's buffer, delivered on the next call
+    pending: Vec<u8>,
+    done: bool,
+}
+
+/// Create a compressor. `size_hint` is the expected input size (0 =
+/// unknown). A non-null `dict_ptr` sets a custom dictionary, which the dcb
+/// format (1) requires.
 #[no_mangle]
-pub unsafe extern "C" fn create_brotli_compressor(level: u32) -> u32 {
-    let handle = next_handle();
+pub unsafe extern "C" fn create_brotli_compressor(
+    level: u32,
+    format: u8,
+    mode: u8,
+    size_hint: usize,
+    dict_ptr: *const u8,
+    dict_len: usize,
+) -> u32 {
+    let Some(format) = brotli_format(format) else {
+        abi::fail(ErrorCode::Failed, format_args!("Unknown brotli format {}", format));
+        return 0;
+    };
+    let Some(mode) = brotli_mode(mode) else {
+        abi::fail(ErrorCode::Failed, format_args!("Unknown brotli mode {}", mode));
+        return 0;
+    };
     let opts = CompressionOptions {
         level: Some(level),
         ..Default::default()
     };
-    match BrotliCompressor::new(opts) {
+    let brotli = BrotliOptions {
+        format,
+        mode,
+        size_hint: (size_hint != 0).then_some(size_hint),
+        dictionary: optional_bytes(dict_ptr, dict_len),
+    };
+    match BrotliCompressor::with_options(opts, brotli) {
         Ok(compressor) => {
-            COMPRESSORS.lock().unwrap().insert(handle, compressor);
-            handle
+            let state = BrotliCompressorState {
+                compressor,
+                pending: Vec::new(),
+                done: false,
+            };
+            insert_handle(state)
+        }
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
         }
-        Err(_) => 0,
+    }
+}
+
+/// Map the `flush` byte of the chunk ABI onto a flush mode:
+/// 0 = none, 1 = finish, 2 = sync, 3 = full.
+fn flush_mode(flush: u8) -> Flush {
+    match flush {
+        0 => Flush::None,
+        2 => Flush::Sync,
+        3 => Flush::Full,
+        _ => Flush::Finish,
     }
 }
 
@@ -104,39 +240,64 @@ pub unsafe extern "C" fn compress_brotli_chunk(
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
-    finish: u8,
+    flush: u8,
 ) -> isize {
-    let mut compressors = COMPRESSORS.lock().unwrap();
-    let compressor = match compressors.get_mut(&handle) {
-        Some(c) => c,
-        None => return -1,
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<BrotliCompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
     };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<BrotliCompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
 
-    let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let flush = if finish != 0 { Flush::Finish } else { Flush::None };
-
-    match compressor.compress_chunk(input, flush) {
-        Ok(out) => {
-            if out.is_empty() {
-                return 0;
+    // Feed new input (and the flush request) to the compressor, queuing its
+    // output behind anything an earlier call couldn't deliver. A call with no
+    // input and no flush only drains.
+    let flush = flush_mode(flush);
+    if !state.done && (in_len > 0 || flush != Flush::None) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.compressor.compress_chunk(input, flush) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<BrotliCompressorState>(handle);
+                    return code;
+                }
             }
-            if out.len() > out_len {
-                return -(out.len() as isize);
-            }
-            std::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len());
-            let result = out.len() as isize;
-            if finish != 0 {
-                compressors.remove(&handle);
+            Err(e) => {
+                let _ = handles.remove::<BrotliCompressorState>(handle);
+                return codec_error(e, ErrorCode::Failed);
             }
-            result
         }
-        Err(_) => -1,
+        if flush == Flush::Finish {
+            state.done = true;
+        }
+    }
+
+    // Deliver all pending output, or report the size needed and keep it
+    // queued for a retry with a bigger buffer.
+    let len = state.pending.len();
+    if len > out_len {
+        return abi::needed_size(len);
+    }
+    std::ptr::copy_nonoverlapping(state.pending.as_ptr(), out_ptr, len);
+    state.pending.clear();
+    if state.done {
+        let _ = handles.remove::<BrotliCompressorState>(handle);
     }
+    len as isize
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn destroy_brotli_compressor(handle: u32) {
-    COMPRESSORS.lock().unwrap().remove(&handle);
+    let _ = HANDLES.lock().unwrap().remove::<BrotliCompressorState>(handle);
 }
 
 // ============================================================================
@@ -144,26 +305,155 @@ pub unsafe extern "C" fn destroy_brotli_compressor(handle: u32) {
 // ============================================================================
 
 #[no_mangle]
+#[allow(clippy::too_many_arguments)]
 pub unsafe extern "C" fn decompress_brotli(
     in_ptr: *const u8,
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
+    format: u8,
+    dict_ptr: *const u8,
+    dict_len: usize,
+    max_output_size: usize,
+    max_ratio: u32,
 ) -> isize {
-    use std::io::Read;
-    
     let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let mut decoder = brotli::Decompressor::new(input, 4096);
-    let mut output = Vec::new();
-    
-    match decoder.read_to_end(&mut output) {
-        Ok(_) => {
+    let Some(brotli) = decompress_options(format, dict_ptr, dict_len) else {
+        return ErrorCode::Failed.code();
+    };
+    let opts = decompression_options(max_output_size, max_ratio);
+
+    let result = BrotliDecompressor::with_options(opts, brotli)
+        .and_then(|mut decoder| decoder.decompress_chunk(input, true));
+    match result {
+        Ok(output) => {
             if output.len() > out_len {
-                return -(output.len() as isize);
+                return abi::needed_size(output.len());
             }
             std::ptr::copy_nonoverlapping(output.as_ptr(), out_ptr, output.len());
             output.len() as isize
         }
-        Err(_) => -1,
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
+    }
+}
+
+// ============================================================================
+// Streaming Decompression API
+// ============================================================================
+
+struct BrotliDecompressorState {
+    decoder: BrotliDecompressor,
+    // Pending decompressed bytes not yet returned to JS
+    pending: Vec<u8>,
+    pending_offset: usize,
+    done: bool,
+}
+
+impl BrotliDecompressorState {
+    fn new(
+        options: DecompressionOptions,
+        brotli: BrotliDecompressOptions,
+    ) -> Result<Self, BrotliError> {
+        let decoder = BrotliDecompressor::with_options(options, brotli)?;
+        Ok(Self {
+            decoder,
+            pending: Vec::new(),
+            pending_offset: 0,
+            done: false,
+        })
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn create_brotli_decompressor(
+    format: u8,
+    dict_ptr: *const u8,
+    dict_len: usize,
+    max_output_size: usize,
+    max_ratio: u32,
+) -> u32 {
+    let Some(brotli) = decompress_options(format, dict_ptr, dict_len) else {
+        return 0;
+    };
+    let opts = decompression_options(max_output_size, max_ratio);
+    match BrotliDecompressorState::new(opts, brotli) {
+        Ok(state) => insert_handle(state),
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn destroy_brotli_decompressor(handle: u32) {
+    let _ = HANDLES.lock().unwrap().remove::<BrotliDecompressorState>(handle);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn decompress_brotli_chunk(
+    handle: u32,
+    in_ptr: *const u8,
+    in_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+    finish: u8,
+) -> isize {
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<BrotliDecompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
+    };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<BrotliDecompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
+
+    // Feed new compressed input (and the end-of-stream signal) to the decoder,
+    // stashing whatever it produces behind any output JS hasn't collected yet.
+    if !state.done && (in_len > 0 || finish != 0) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.decoder.decompress_chunk(input, finish != 0) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<BrotliDecompressorState>(handle);
+                    return code;
+                }
+            }
+            Err(e) => {
+                let _ = handles.remove::<BrotliDecompressorState>(handle);
+                return codec_error(e, ErrorCode::CorruptData);
+            }
+        }
+        if finish != 0 {
+            state.done = true;
+        }
+    }
+
+    // Drain pending output into the caller's buffer.
+    let remaining = state.pending.len() - state.pending_offset;
+    let to_copy = remaining.min(out_len);
+    if to_copy > 0 {
+        std::ptr::copy_nonoverlapping(
+            state.pending.as_ptr().add(state.pending_offset),
+            out_ptr,
+            to_copy,
+        );
+        state.pending_offset += to_copy;
+    }
+    // If fully drained, reset buffer to avoid unbounded growth.
+    if state.pending_offset >= state.pending.len() {
+        state.pending.clear();
+        state.pending_offset = 0;
+        if state.done && to_copy == 0 {
+            let _ = handles.remove::<BrotliDecompressorState>(handle);
+        }
     }
+    to_copy as isize
 }
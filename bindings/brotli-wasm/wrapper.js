@@ -1,8 +1,4 @@
-import { 
-  compress_level_1, 
-  compress_level_4, 
-  compress_level_6, 
-  compress_level_9,
+import {
   decompress_brotli,
   create_brotli_compressor,
   compress_brotli_chunk,
@@ -14,6 +10,34 @@ import {
   ensureReady
 } from './core.js';
 
+// Flush modes understood by compress_brotli_chunk
+const FLUSH_NONE = 0;
+const FLUSH_FINISH = 1;
+const FLUSH_SYNC = 2;
+const FLUSH_FULL = 3;
+
+// Containers understood by the create/decompress exports
+const FORMATS = { br: 0, dcb: 1 };
+
+function formatCode(format = 'br') {
+  const code = FORMATS[format];
+  if (code === undefined) {
+    throw new Error(`Unknown format "${format}" (expected br or dcb)`);
+  }
+  return code;
+}
+
+// Encoder modes understood by create_brotli_compressor
+const MODES = { generic: 0, text: 1, font: 2 };
+
+function modeCode(mode = 'generic') {
+  const code = MODES[mode];
+  if (code === undefined) {
+    throw new Error(`Unknown mode "${mode}" (expected generic, text or font)`);
+  }
+  return code;
+}
+
 function toBytes(input) {
   if (input instanceof Uint8Array) return input;
   if (ArrayBuffer.isView(input)) return new Uint8Array(input.buffer, input.byteOffset, input.byteLength);
@@ -21,19 +45,134 @@ function toBytes(input) {
   throw new TypeError("Expected a TypedArray or ArrayBuffer");
 }
 
+// Copy an optional byte field into wasm memory; absent fields are passed as
+// null pointers.
+function copyField(value) {
+  if (value === undefined || value === null) return { ptr: 0, len: 0 };
+  const bytes = toBytes(value);
+  const ptr = alloc(Math.max(bytes.byteLength, 1));
+  memoryU8().set(bytes, ptr);
+  return { ptr, len: bytes.byteLength };
+}
+
+function freeField({ ptr, len }) {
+  if (ptr !== 0) free(ptr, Math.max(len, 1));
+}
+
+// Error codes returned by the exports (`core_api::abi::ErrorCode`); any
+// other negative value is the output size needed.
+const ERROR_CODES = {
+  [-1]: 'FAILED',
+  [-2]: 'LIMIT_EXCEEDED',
+  [-3]: 'INVALID_HANDLE',
+  [-4]: 'CORRUPT_DATA',
+  [-5]: 'CHECKSUM_MISMATCH',
+  [-6]: 'TRUNCATED_INPUT',
+  [-7]: 'ALREADY_FINISHED',
+  [-8]: 'OUT_OF_MEMORY',
+};
+const LOWEST_ERROR_CODE = -8;
+
+function isErrorCode(value) {
+  return value < 0 && value >= LOWEST_ERROR_CODE;
+}
+
+/**
+ * Error thrown when a call into the wasm module fails. `code` is one of the
+ * names in ERROR_CODES, e.g. 'CORRUPT_DATA' or 'LIMIT_EXCEEDED'.
+ */
+export class CodecError extends Error {
+  constructor(message, code) {
+    super(message);
+    this.name = 'CodecError';
+    this.code = code;
+  }
+}
+
+// The module's description of its last failure.
+function lastErrorMessage() {
+  let outLen = 256;
+  let outPtr = alloc(outLen);
+  try {
+    let written = wasmExports().last_error_message(outPtr, outLen);
+    if (written < 0) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = wasmExports().last_error_message(outPtr, outLen);
+    }
+    return new TextDecoder().decode(memoryU8().subarray(outPtr, outPtr + Math.max(written, 0)));
+  } finally {
+    free(outPtr, outLen);
+  }
+}
+
+// Error for an export that returned `code`; create exports, which return 0,
+// leave it to the module's last error code.
+function exportError(context, code = wasmExports().last_error_code()) {
+  return new CodecError(`${context}: ${lastErrorMessage()}`, ERROR_CODES[code] ?? 'FAILED');
+}
+
+// Error for a call on a stream that was finished or destroyed in JS already.
+// A chunk export that fails releases the stream's handle, for compressors and
+// decompressors alike, so a stream is closed after any error and later calls
+// get this error too.
+function closedError(kind, finished) {
+  return finished
+    ? new CodecError(`${kind} already finished`, 'ALREADY_FINISHED')
+    : new CodecError(`${kind} already destroyed`, 'INVALID_HANDLE');
+}
+
 // ============================================================================
 // One-shot Compression API
 // ============================================================================
 
+// Compress with the one-shot export, growing the output buffer once if the
+// result doesn't fit.
+function compressOneShot(input, level) {
+  const view = toBytes(input);
+  const len = view.byteLength;
+  let outLen = len + 1024;
+  const inPtr = alloc(len);
+  let outPtr = alloc(outLen);
+  try {
+    memoryU8().set(view, inPtr);
+    let written = wasmExports().compress_brotli(inPtr, len, outPtr, outLen, level);
+    if (written < LOWEST_ERROR_CODE) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = wasmExports().compress_brotli(inPtr, len, outPtr, outLen, level);
+    }
+    if (written < 0) {
+      throw exportError('Compression failed', written);
+    }
+    return memoryU8().slice(outPtr, outPtr + written);
+  } finally {
+    free(inPtr, len);
+    free(outPtr, outLen);
+  }
+}
+
 export async function compress(input, options = {}) {
   const level = options.level ?? 9;
   
   try {
-    if (level <= 1) return compress_level_1(input);
-    if (level <= 4) return compress_level_4(input);
-    if (level <= 6) return compress_level_6(input);
-    return compress_level_9(input);
+    if (
+      formatCode(options.format) !== FORMATS.br ||
+      modeCode(options.mode) !== MODES.generic ||
+      options.dictionary !== undefined
+    ) {
+      // The one-shot exports write plain generic-mode brotli; other modes
+      // and dictionaries use a single-use handle, sized for the input.
+      const sizeHint = toBytes(input).byteLength;
+      const enc = new StreamingCompressor({ sizeHint, ...options, level });
+      return await enc.compressChunk(input, true);
+    }
+    await ensureReady();
+    return compressOneShot(input, level);
   } catch (error) {
+    if (error.code) throw error;
     throw new Error(`Compression failed: ${error.message}`);
   }
 }
@@ -42,23 +181,33 @@ export async function compress(input, options = {}) {
 // One-shot Decompression API
 // ============================================================================
 
-export async function decompress(input) {
+export async function decompress(input, options = {}) {
   await ensureReady();
   
+  const format = formatCode(options.format);
   const view = toBytes(input);
   const len = view.byteLength;
+  const maxOutputSize = options.maxOutputSize ?? 0;
+  const maxRatio = options.maxRatio ?? 0;
   
   // Start with a reasonable estimate (100x compressed size)
   let outLen = Math.max(len * 100, 65536);
   
   const inPtr = alloc(len);
   let outPtr = alloc(outLen);
+  const dict = copyField(options.dictionary);
+  const run = () => wasmExports().decompress_brotli(
+    inPtr, len, outPtr, outLen, format, dict.ptr, dict.len, maxOutputSize, maxRatio
+  );
   
   try {
     memoryU8().set(view, inPtr);
-    let written = wasmExports().decompress_brotli(inPtr, len, outPtr, outLen);
+    let written = run();
+    if (isErrorCode(written)) {
+      throw exportError('Decompression failed', written);
+    }
     
-    // Negative value means we need more space
+    // Any other negative value means we need more space
     if (written < 0) {
       const neededLen = -written;
       free(outPtr, outLen);
@@ -66,20 +215,23 @@ export async function decompress(input) {
       outPtr = alloc(outLen);
       
       // Retry with correct size
-      written = wasmExports().decompress_brotli(inPtr, len, outPtr, outLen);
+      written = run();
       
       if (written < 0) {
-        throw new Error('Decompression failed after resize');
+        throw exportError('Decompression failed', written);
       }
     }
     
     const result = memoryU8().slice(outPtr, outPtr + written);
     free(inPtr, len);
     free(outPtr, outLen);
+    freeField(dict);
     return result;
   } catch (error) {
     free(inPtr, len);
     free(outPtr, outLen);
+    freeField(dict);
+    if (error.code) throw error;
     throw new Error(`Decompression failed: ${error.message}`);
   }
 }
@@ -92,23 +244,56 @@ export class StreamingCompressor {
   constructor(options = {}) {
     this._initPromise = ensureReady();
     this.level = options.level ?? 6;
+    this.format = formatCode(options.format);
+    this.mode = modeCode(options.mode);
+    // Expected total input size; 0 = unknown.
+    this.sizeHint = options.sizeHint ?? 0;
+    this.dictionary = options.dictionary;
+    if (this.format === FORMATS.dcb && this.dictionary === undefined) {
+      throw new Error('The dcb format needs a dictionary');
+    }
     this.handle = null;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
   }
 
   async _ensureInit() {
     await this._initPromise;
     if (this.handle === null) {
-      this.handle = wasmExports().create_brotli_compressor(this.level);
+      const dict = copyField(this.dictionary);
+      try {
+        this.handle = wasmExports().create_brotli_compressor(
+          this.level, this.format, this.mode, this.sizeHint, dict.ptr, dict.len
+        );
+      } finally {
+        freeField(dict);
+      }
       if (this.handle === 0) {
-        throw new Error('Failed to create compressor');
+        throw exportError('Failed to create compressor');
       }
     }
   }
 
   async compressChunk(input, finish = false) {
+    return this._compressChunk(input, finish ? FLUSH_FINISH : FLUSH_NONE);
+  }
+
+  /**
+   * Compress `input` and force out everything written so far, so a receiver
+   * can decode it, without ending the stream.
+   * @param {Uint8Array} [input]
+   * @param {{ full?: boolean }} [options] full: treated like a sync flush (no resettable history)
+   * @returns {Promise<Uint8Array>}
+   */
+  async flush(input = new Uint8Array(0), options = {}) {
+    return this._compressChunk(input, options.full ? FLUSH_FULL : FLUSH_SYNC);
+  }
+
+  async _compressChunk(input, mode) {
+    const finish = mode === FLUSH_FINISH;
     await this._ensureInit();
     if (this.handle === 0) {
-      throw new Error('Compressor already destroyed');
+      throw closedError('Compressor', this.finished);
     }
     
     const view = toBytes(input);
@@ -120,26 +305,32 @@ export class StreamingCompressor {
     
     try {
       memoryU8().set(view, inPtr);
-      const written = wasmExports().compress_brotli_chunk(this.handle, inPtr, len, outPtr, outLen, finish ? 1 : 0);
+      const written = wasmExports().compress_brotli_chunk(this.handle, inPtr, len, outPtr, outLen, mode);
       
       if (written < 0) {
-        if (written === -1) {
-          throw new Error('Compression failed');
+        if (isErrorCode(written)) {
+          this.handle = 0;
+          throw exportError('Compression failed', written);
         } else {
           // Negative value indicates needed buffer size
           free(outPtr, outLen);
           const neededLen = -written;
           const newOutPtr = alloc(neededLen);
-          memoryU8().set(view, inPtr);
-          const retryWritten = wasmExports().compress_brotli_chunk(this.handle, inPtr, len, newOutPtr, neededLen, finish ? 1 : 0);
+          // The output is queued on the Rust side; collect it without
+          // feeding the input again.
+          const retryWritten = wasmExports().compress_brotli_chunk(this.handle, inPtr, 0, newOutPtr, neededLen, FLUSH_NONE);
           if (retryWritten < 0) {
             free(newOutPtr, neededLen);
-            throw new Error('Compression failed after retry');
+            this.handle = 0;
+            throw exportError('Compression failed after retry', retryWritten);
           }
           const result = memoryU8().slice(newOutPtr, newOutPtr + retryWritten);
           free(newOutPtr, neededLen);
           free(inPtr, len);
-          if (finish) this.handle = 0;
+          if (finish) {
+            this.handle = 0;
+            this.finished = true;
+          }
           return result;
         }
       }
@@ -154,11 +345,15 @@ export class StreamingCompressor {
       free(outPtr, outLen);
       free(inPtr, len);
       
-      if (finish) this.handle = 0;
+      if (finish) {
+        this.handle = 0;
+        this.finished = true;
+      }
       return result;
     } catch (error) {
       free(outPtr, outLen);
       free(inPtr, len);
+      if (error.code) throw error;
       throw new Error(`Compression failed: ${error.message}`);
     }
   }
@@ -172,6 +367,156 @@ export class StreamingCompressor {
   }
 }
 
+// ============================================================================
+// Streaming Decompression API (WASM)
+// ============================================================================
+
+export class StreamingDecompressor {
+  constructor(options = {}) {
+    this._initPromise = ensureReady();
+    this.maxOutputSize = options.maxOutputSize ?? 0;
+    this.maxRatio = options.maxRatio ?? 0;
+    this.format = formatCode(options.format);
+    this.dictionary = options.dictionary;
+    this.handle = null;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
+  }
+
+  async _ensureInit() {
+    await this._initPromise;
+    if (this.handle === null) {
+      const dict = copyField(this.dictionary);
+      try {
+        this.handle = wasmExports().create_brotli_decompressor(
+          this.format, dict.ptr, dict.len, this.maxOutputSize, this.maxRatio
+        );
+      } finally {
+        freeField(dict);
+      }
+      if (this.handle === 0) {
+        throw exportError('Failed to create decompressor');
+      }
+    }
+  }
+
+  async decompressChunk(input, finish = false) {
+    await this._ensureInit();
+    if (this.handle === 0) {
+      throw closedError('Decompressor', this.finished);
+    }
+
+    const view = toBytes(input);
+    const len = view.byteLength;
+
+    // Heuristic: brotli can expand a lot; start with a decent minimum.
+    const outLen = Math.max(len * 8, 65536);
+
+    const inPtr = alloc(len);
+    const outPtr = alloc(outLen);
+
+    try {
+      memoryU8().set(view, inPtr);
+      const written = wasmExports().decompress_brotli_chunk(
+        this.handle,
+        inPtr,
+        len,
+        outPtr,
+        outLen,
+        finish ? 1 : 0
+      );
+
+      if (written < 0) {
+        this.handle = 0;
+        throw exportError('Decompression failed', written);
+      }
+
+      if (written === 0) {
+        free(inPtr, len);
+        free(outPtr, outLen);
+        if (finish) {
+          this.handle = 0;
+          this.finished = true;
+        }
+        return new Uint8Array(0);
+      }
+
+      // Output may still be pending on the Rust side after finish; the handle
+      // is released once a call returns 0, so keep it until then.
+      const result = memoryU8().slice(outPtr, outPtr + written);
+      free(inPtr, len);
+      free(outPtr, outLen);
+      return result;
+    } catch (error) {
+      free(inPtr, len);
+      free(outPtr, outLen);
+      if (error.code) throw error;
+      throw new Error(`Decompression failed: ${error.message}`);
+    }
+  }
+
+  async destroy() {
+    await this._initPromise;
+    if (this.handle !== 0 && this.handle !== null) {
+      wasmExports().destroy_brotli_decompressor(this.handle);
+      this.handle = 0;
+    }
+  }
+}
+
+// ============================================================================
+// Ergonomic streaming helpers (Web Streams)
+// ============================================================================
+
+function requireTransformStream() {
+  if (typeof TransformStream === 'undefined') {
+    throw new Error('TransformStream is not available in this runtime');
+  }
+}
+
+/**
+ * Create a TransformStream that brotli-decompresses a byte stream.
+ *
+ * @param {{ format?: 'br' | 'dcb', dictionary?: Uint8Array, maxOutputSize?: number, maxRatio?: number }} [options]
+ * @returns {TransformStream<Uint8Array, Uint8Array>}
+ */
+export function createDecompressionStream(options = {}) {
+  requireTransformStream();
+  const dec = new StreamingDecompressor(options);
+
+  async function drain(controller, finish) {
+    // Drain any remaining output buffered on the Rust side.
+    while (true) {
+      const out = await dec.decompressChunk(new Uint8Array(0), finish);
+      if (!out.length) break;
+      controller.enqueue(out);
+      // Only pass finish once; subsequent drains should be finish=false.
+      finish = false;
+    }
+  }
+
+  return new TransformStream({
+    async transform(chunk, controller) {
+      const out = await dec.decompressChunk(toBytes(chunk), false);
+      if (out.length) controller.enqueue(out);
+      await drain(controller, false);
+    },
+    async flush(controller) {
+      // Finish and drain the remainder.
+      await drain(controller, true);
+    },
+  });
+}
+
+/**
+ * Convenience helper: readable.pipeThrough(createDecompressionStream()).
+ * @param {ReadableStream<Uint8Array>} readable
+ * @param {{ format?: 'br' | 'dcb', dictionary?: Uint8Array, maxOutputSize?: number, maxRatio?: number }} [options]
+ */
+export function decompressStream(readable, options = {}) {
+  return readable.pipeThrough(createDecompressionStream(options));
+}
+
 export function getLoadedVariant() {
   return 'lite';
 }
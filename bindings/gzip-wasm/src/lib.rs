@@ -1,9 +1,14 @@
-use codec_gzip::GzipCompressor;
-use core_api::{CompressionOptions, Compressor, Flush};
-use std::collections::HashMap;
+use codec_gzip::{
+    BgzfReader, DeflateStrategy, GzipCompressor, GzipDecompressOptions, GzipDecompressor,
+    GzipError, GzipFormat, GzipHeader, GzipMember, GzipOptions,
+};
+use core_api::abi::{self, ErrorCode};
+use core_api::handles::{HandleKind, HandleRegistry};
+use core_api::{
+    CompressionOptions, Compressor, DecompressionOptions, Decompressor, ErrorKind, Flush,
+    HasErrorKind,
+};
 use std::sync::Mutex;
-use std::sync::LazyLock;
-use std::io::Read;
 
 // --- wasm-bindgen-lite bindings ---
 
@@ -21,57 +26,190 @@ pub unsafe extern "C" fn free_bytes(ptr: *mut u8, len: usize) {
     dealloc(ptr, layout);
 }
 
-// Static storage for streaming compressors
-static COMPRESSORS: LazyLock<Mutex<HashMap<u32, GzipCompressor>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
-static DECOMPRESSORS: LazyLock<Mutex<HashMap<u32, GzipDecompressorState>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
-static mut HANDLE_COUNTER: u32 = 1;
+/// Code of the last failed call (see `core_api::abi::ErrorCode`).
+#[no_mangle]
+pub extern "C" fn last_error_code() -> i32 {
+    abi::last_error_code() as i32
+}
+
+/// Copy the UTF-8 description of the last failed call into `out_ptr`.
+/// Returns its length, or the negative size needed.
+#[no_mangle]
+pub unsafe extern "C" fn last_error_message(out_ptr: *mut u8, out_len: usize) -> isize {
+    let message = abi::last_error_message();
+    if message.len() > out_len {
+        return abi::needed_size(message.len());
+    }
+    std::ptr::copy_nonoverlapping(message.as_ptr(), out_ptr, message.len());
+    message.len() as isize
+}
+
+// Static storage for streaming compressors and decompressors
+core_api::handle_entries! {
+    enum Entry {
+        Compressor(GzipCompressorState),
+        Decompressor(GzipDecompressorState),
+    }
+}
+
+static HANDLES: Mutex<HandleRegistry<Entry>> = Mutex::new(HandleRegistry::new());
+
+/// Store a new object and return its handle, or 0 when every handle is taken.
+fn insert_handle<K: HandleKind<Entry>>(value: K) -> u32 {
+    HANDLES.lock().unwrap().insert(value).unwrap_or_else(|| {
+        abi::fail(ErrorCode::Failed, "Too many open handles");
+        0
+    })
+}
+
+/// Record a codec failure for `last_error_message` and return its ABI code;
+/// `fallback` is the code for io errors the codec can't classify further.
+fn codec_error(e: GzipError, fallback: ErrorCode) -> isize {
+    let code = match e.kind() {
+        ErrorKind::Io => fallback,
+        kind => ErrorCode::from(kind),
+    };
+    abi::fail(code, e)
+}
+
+/// Queue `out` behind the output JS hasn't collected yet, failing rather
+/// than aborting when memory runs out.
+fn queue_output(pending: &mut Vec<u8>, out: &[u8]) -> Result<(), isize> {
+    if pending.try_reserve(out.len()).is_err() {
+        return Err(abi::fail(ErrorCode::OutOfMemory, "Out of memory for pending output"));
+    }
+    pending.extend_from_slice(out);
+    Ok(())
+}
+
+/// Build decompression limits from the ABI arguments; 0 means unlimited.
+fn decompression_options(max_output_size: usize, max_ratio: u32) -> DecompressionOptions {
+    DecompressionOptions {
+        max_output_size: (max_output_size != 0).then_some(max_output_size as u64),
+        max_ratio: (max_ratio != 0).then_some(max_ratio),
+    }
+}
+
+/// Map the `format` byte of the ABI onto a container:
+/// 0 = gzip, 1 = zlib, 2 = raw deflate, 3 = BGZF.
+fn gzip_format(format: u8) -> Option<GzipFormat> {
+    match format {
+        0 => Some(GzipFormat::Gzip),
+        1 => Some(GzipFormat::Zlib),
+        2 => Some(GzipFormat::Raw),
+        3 => Some(GzipFormat::Bgzf),
+        _ => None,
+    }
+}
 
-fn next_handle() -> u32 {
-    unsafe {
-        let handle = HANDLE_COUNTER;
-        HANDLE_COUNTER += 1;
-        handle
+/// Map the `strategy` byte of the ABI onto a deflate strategy, numbered as
+/// zlib's: 0 = default, 1 = filtered, 2 = Huffman only, 3 = RLE, 4 = fixed.
+fn deflate_strategy(strategy: u8) -> Option<DeflateStrategy> {
+    match strategy {
+        0 => Some(DeflateStrategy::Default),
+        1 => Some(DeflateStrategy::Filtered),
+        2 => Some(DeflateStrategy::HuffmanOnly),
+        3 => Some(DeflateStrategy::Rle),
+        4 => Some(DeflateStrategy::Fixed),
+        _ => None,
     }
 }
 
+/// Read an optional byte field of the ABI; a null pointer means absent.
+unsafe fn optional_bytes(ptr: *const u8, len: usize) -> Option<Vec<u8>> {
+    if ptr.is_null() {
+        None
+    } else if len == 0 {
+        Some(Vec::new())
+    } else {
+        Some(std::slice::from_raw_parts(ptr, len).to_vec())
+    }
+}
+
+/// Copy a gzip header to JS in its on-the-wire encoding, which the wrapper
+/// decodes; negative = needed size.
+unsafe fn write_header(header: &GzipHeader, out_ptr: *mut u8, out_len: usize) -> isize {
+    let bytes = header.to_bytes();
+    if bytes.len() > out_len {
+        return abi::needed_size(bytes.len());
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), out_ptr, bytes.len());
+    bytes.len() as isize
+}
+
 // ============================================================================
 // Streaming Decompression API
 // ============================================================================
 
-use flate2::bufread::GzDecoder;
-use std::io::Cursor;
-
 struct GzipDecompressorState {
-    decoder: GzDecoder<Cursor<Vec<u8>>>,
+    decoder: GzipDecompressor,
     // Pending decompressed bytes not yet returned to JS
     pending: Vec<u8>,
     pending_offset: usize,
+    // Completed gzip members not yet returned to JS
+    members: Vec<GzipMember>,
     done: bool,
 }
 
 impl GzipDecompressorState {
-    fn new() -> Self {
-        let cursor = Cursor::new(Vec::new());
-        let decoder = GzDecoder::new(cursor);
-        Self {
+    fn new(options: DecompressionOptions, gzip: GzipDecompressOptions) -> Result<Self, GzipError> {
+        let decoder = GzipDecompressor::with_options(options, gzip)?;
+        Ok(Self {
             decoder,
             pending: Vec::new(),
             pending_offset: 0,
+            members: Vec::new(),
             done: false,
-        }
+        })
     }
 }
 
+/// Gzip decode settings from the ABI; `multi_member` 0 stops after the first
+/// member and a null `dict_ptr` means no preset dictionary. Records the
+/// error for an unknown format.
+unsafe fn decompress_options(
+    format: u8,
+    multi_member: u8,
+    dict_ptr: *const u8,
+    dict_len: usize,
+) -> Option<GzipDecompressOptions> {
+    let Some(format) = gzip_format(format) else {
+        abi::fail(ErrorCode::Failed, format_args!("Unknown gzip format {}", format));
+        return None;
+    };
+    Some(GzipDecompressOptions {
+        format,
+        multi_member: multi_member != 0,
+        dictionary: optional_bytes(dict_ptr, dict_len),
+    })
+}
+
 #[no_mangle]
-pub unsafe extern "C" fn create_gzip_decompressor() -> u32 {
-    let handle = next_handle();
-    DECOMPRESSORS.lock().unwrap().insert(handle, GzipDecompressorState::new());
-    handle
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn create_gzip_decompressor(
+    format: u8,
+    multi_member: u8,
+    dict_ptr: *const u8,
+    dict_len: usize,
+    max_output_size: usize,
+    max_ratio: u32,
+) -> u32 {
+    let Some(gzip) = decompress_options(format, multi_member, dict_ptr, dict_len) else {
+        return 0;
+    };
+    let opts = decompression_options(max_output_size, max_ratio);
+    match GzipDecompressorState::new(opts, gzip) {
+        Ok(state) => insert_handle(state),
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
+        }
+    }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn destroy_gzip_decompressor(handle: u32) {
-    DECOMPRESSORS.lock().unwrap().remove(&handle);
+    let _ = HANDLES.lock().unwrap().remove::<GzipDecompressorState>(handle);
 }
 
 #[no_mangle]
@@ -83,85 +221,124 @@ pub unsafe extern "C" fn decompress_gzip_chunk(
     out_len: usize,
     finish: u8,
 ) -> isize {
-    let mut decompressors = DECOMPRESSORS.lock().unwrap();
-    let state = match decompressors.get_mut(&handle) {
-        Some(s) => s,
-        None => return -1,
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<GzipDecompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
     };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<GzipDecompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
 
-    // If we already have pending output, drain that first.
-    if state.pending_offset < state.pending.len() {
-        let remaining = state.pending.len() - state.pending_offset;
-        let to_copy = remaining.min(out_len);
-        if to_copy > 0 {
-            std::ptr::copy_nonoverlapping(
-                state.pending.as_ptr().add(state.pending_offset),
-                out_ptr,
-                to_copy,
-            );
-            state.pending_offset += to_copy;
-            // If fully drained, reset buffer to avoid unbounded growth.
-            if state.pending_offset >= state.pending.len() {
-                state.pending.clear();
-                state.pending_offset = 0;
+    // Feed new compressed input (and the end-of-stream signal) to the decoder,
+    // stashing whatever it produces behind any output JS hasn't collected yet.
+    if !state.done && (in_len > 0 || finish != 0) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.decoder.decompress_chunk(input, finish != 0) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<GzipDecompressorState>(handle);
+                    return code;
+                }
+                state.members.append(&mut state.decoder.take_members());
+            }
+            Err(e) => {
+                let _ = handles.remove::<GzipDecompressorState>(handle);
+                return codec_error(e, ErrorCode::CorruptData);
             }
-            return to_copy as isize;
         }
-        return 0;
+        if finish != 0 {
+            state.done = true;
+        }
     }
 
-    // Append new compressed input to the underlying cursor buffer.
-    if in_len > 0 {
-        let input = std::slice::from_raw_parts(in_ptr, in_len);
-        let cursor = state.decoder.get_mut();
-        cursor.get_mut().extend_from_slice(input);
+    // Drain pending output into the caller's buffer.
+    let remaining = state.pending.len() - state.pending_offset;
+    let to_copy = remaining.min(out_len);
+    if to_copy > 0 {
+        std::ptr::copy_nonoverlapping(
+            state.pending.as_ptr().add(state.pending_offset),
+            out_ptr,
+            to_copy,
+        );
+        state.pending_offset += to_copy;
+    }
+    // If fully drained, reset buffer to avoid unbounded growth. A finished
+    // stream keeps its handle, so JS can still collect the header and the
+    // last members, until `destroy_gzip_decompressor`.
+    if state.pending_offset >= state.pending.len() {
+        state.pending.clear();
+        state.pending_offset = 0;
     }
+    to_copy as isize
+}
 
-    // Try to read some decompressed output.
-    // Read into a temporary buffer and either return it immediately or stash it.
-    let mut buf = vec![0u8; out_len.max(8192)];
-    match state.decoder.read(&mut buf) {
-        Ok(0) => {
-            // End of stream (or nothing available). If finish, close handle.
-            state.done = true;
-            if finish != 0 {
-                decompressors.remove(&handle);
-            }
-            0
-        }
-        Ok(n) => {
-            buf.truncate(n);
-            // If caller out buffer is smaller than what we read (can happen if out_len < 8192),
-            // store remainder and return partial. Otherwise return all.
-            if n > out_len {
-                let to_copy = out_len;
-                std::ptr::copy_nonoverlapping(buf.as_ptr(), out_ptr, to_copy);
-                state.pending = buf;
-                state.pending_offset = to_copy;
-                to_copy as isize
-            } else {
-                std::ptr::copy_nonoverlapping(buf.as_ptr(), out_ptr, n);
-                n as isize
-            }
-        }
-        Err(e) => {
-            // For incomplete streams, flate2 typically reports UnexpectedEof.
-            if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                if finish != 0 {
-                    decompressors.remove(&handle);
-                    return -1;
-                }
-                return 0;
-            }
-            if finish != 0 {
-                decompressors.remove(&handle);
-            }
-            -1
+/// Size of one record written by `gzip_decompressor_members`.
+const MEMBER_RECORD_LEN: usize = 32;
+
+/// Copy the boundaries of the gzip members a streaming decompressor has
+/// completed since the last call into `out_ptr`, as little-endian u64
+/// records of (compressed offset, compressed length, output offset, output
+/// length). Returns the bytes written (0 if none), an error code, or the
+/// negative size needed, in which case the members stay queued.
+#[no_mangle]
+pub unsafe extern "C" fn gzip_decompressor_members(
+    handle: u32,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<GzipDecompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
+    };
+    let len = state.members.len() * MEMBER_RECORD_LEN;
+    if len > out_len {
+        return abi::needed_size(len);
+    }
+    let out = std::slice::from_raw_parts_mut(out_ptr, len);
+    for (record, member) in out.chunks_exact_mut(MEMBER_RECORD_LEN).zip(state.members.drain(..)) {
+        let fields = [
+            member.compressed_offset,
+            member.compressed_len,
+            member.output_offset,
+            member.output_len,
+        ];
+        for (slot, value) in record.chunks_exact_mut(8).zip(fields) {
+            slot.copy_from_slice(&value.to_le_bytes());
         }
     }
+    len as isize
 }
 
-unsafe fn compress_gzip_raw(
+/// Copy the gzip header of a streaming decompressor into `out_ptr`.
+/// Returns 0 until the header has been received, or an error code.
+#[no_mangle]
+pub unsafe extern "C" fn gzip_decompressor_header(
+    handle: u32,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    let handles = HANDLES.lock().unwrap();
+    match handles.get::<GzipDecompressorState>(handle) {
+        Ok(state) => match state.decoder.header() {
+            Some(header) => write_header(header, out_ptr, out_len),
+            None => 0,
+        },
+        Err(e) => abi::fail(ErrorCode::InvalidHandle, e),
+    }
+}
+
+/// One-shot gzip compression at any level (0 = stored, 9 = best).
+#[no_mangle]
+pub unsafe extern "C" fn compress_gzip(
     in_ptr: *const u8,
     in_len: usize,
     out_ptr: *mut u8,
@@ -177,47 +354,122 @@ unsafe fn compress_gzip_raw(
     match <GzipCompressor as Compressor>::compress_all(input, opts) {
         Ok(out) => {
             if out.len() > out_len {
-                return -(out.len() as isize);
+                return abi::needed_size(out.len());
             }
             std::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len());
             out.len() as isize
         }
-        Err(_) => -1,
+        Err(e) => codec_error(e, ErrorCode::Failed),
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn compress_gzip_level_1(p: *const u8, l: usize, o: *mut u8, ol: usize) -> isize {
-    compress_gzip_raw(p, l, o, ol, 1)
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn compress_gzip_level_6(p: *const u8, l: usize, o: *mut u8, ol: usize) -> isize {
-    compress_gzip_raw(p, l, o, ol, 6)
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn compress_gzip_level_9(p: *const u8, l: usize, o: *mut u8, ol: usize) -> isize {
-    compress_gzip_raw(p, l, o, ol, 9)
-}
-
 // ============================================================================
 // Streaming Compression API
 // ============================================================================
 
-#[no_mangle]
-pub unsafe extern "C" fn create_gzip_compressor(level: u32) -> u32 {
-    let handle = next_handle();
+struct GzipCompressorState {
+    compressor: GzipCompressor,
+    // Compressed bytes that didn't fit the caller's buffer, delivered on the next call
+    pending: Vec<u8>,
+    done: bool,
+}
+
+fn insert_compressor(level: u32, gzip: GzipOptions) -> u32 {
     let opts = CompressionOptions {
         level: Some(level),
         ..Default::default()
     };
-    match GzipCompressor::new(opts) {
+    match GzipCompressor::with_options(opts, gzip) {
         Ok(compressor) => {
-            COMPRESSORS.lock().unwrap().insert(handle, compressor);
-            handle
+            let state = GzipCompressorState {
+                compressor,
+                pending: Vec::new(),
+                done: false,
+            };
+            insert_handle(state)
         }
-        Err(_) => 0,
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
+        }
+    }
+}
+
+/// Create a compressor for the given container. A non-null `dict_ptr` sets
+/// a preset dictionary (zlib and raw deflate only).
+#[no_mangle]
+pub unsafe extern "C" fn create_gzip_compressor(
+    level: u32,
+    format: u8,
+    strategy: u8,
+    dict_ptr: *const u8,
+    dict_len: usize,
+) -> u32 {
+    let Some(format) = gzip_format(format) else {
+        abi::fail(ErrorCode::Failed, format_args!("Unknown gzip format {}", format));
+        return 0;
+    };
+    let Some(strategy) = deflate_strategy(strategy) else {
+        abi::fail(ErrorCode::Failed, format_args!("Unknown deflate strategy {}", strategy));
+        return 0;
+    };
+    insert_compressor(
+        level,
+        GzipOptions {
+            format,
+            strategy,
+            dictionary: optional_bytes(dict_ptr, dict_len),
+            ..Default::default()
+        },
+    )
+}
+
+/// Create a gzip compressor that writes the given header fields. Null
+/// pointers leave the filename, comment and extra field out.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn create_gzip_compressor_with_header(
+    level: u32,
+    strategy: u8,
+    mtime: u32,
+    os: u8,
+    filename_ptr: *const u8,
+    filename_len: usize,
+    comment_ptr: *const u8,
+    comment_len: usize,
+    extra_ptr: *const u8,
+    extra_len: usize,
+) -> u32 {
+    let Some(strategy) = deflate_strategy(strategy) else {
+        abi::fail(ErrorCode::Failed, format_args!("Unknown deflate strategy {}", strategy));
+        return 0;
+    };
+    let header = GzipHeader {
+        filename: optional_bytes(filename_ptr, filename_len),
+        mtime,
+        comment: optional_bytes(comment_ptr, comment_len),
+        extra: optional_bytes(extra_ptr, extra_len),
+        os,
+    };
+    insert_compressor(
+        level,
+        GzipOptions {
+            format: GzipFormat::Gzip,
+            strategy,
+            header,
+            dictionary: None,
+        },
+    )
+}
+
+/// Map the `flush` byte of the chunk ABI onto a flush mode:
+/// 0 = none, 1 = finish, 2 = sync, 3 = full.
+fn flush_mode(flush: u8) -> Flush {
+    match flush {
+        0 => Flush::None,
+        2 => Flush::Sync,
+        3 => Flush::Full,
+        _ => Flush::Finish,
     }
 }
 
@@ -228,39 +480,64 @@ pub unsafe extern "C" fn compress_gzip_chunk(
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
-    finish: u8,
+    flush: u8,
 ) -> isize {
-    let mut compressors = COMPRESSORS.lock().unwrap();
-    let compressor = match compressors.get_mut(&handle) {
-        Some(c) => c,
-        None => return -1,
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<GzipCompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
     };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<GzipCompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
 
-    let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let flush = if finish != 0 { Flush::Finish } else { Flush::None };
-
-    match compressor.compress_chunk(input, flush) {
-        Ok(out) => {
-            if out.is_empty() {
-                return 0;
-            }
-            if out.len() > out_len {
-                return -(out.len() as isize);
+    // Feed new input (and the flush request) to the compressor, queuing its
+    // output behind anything an earlier call couldn't deliver. A call with no
+    // input and no flush only drains.
+    let flush = flush_mode(flush);
+    if !state.done && (in_len > 0 || flush != Flush::None) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.compressor.compress_chunk(input, flush) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<GzipCompressorState>(handle);
+                    return code;
+                }
             }
-            std::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len());
-            let result = out.len() as isize;
-            if finish != 0 {
-                compressors.remove(&handle);
+            Err(e) => {
+                let _ = handles.remove::<GzipCompressorState>(handle);
+                return codec_error(e, ErrorCode::Failed);
             }
-            result
         }
-        Err(_) => -1,
+        if flush == Flush::Finish {
+            state.done = true;
+        }
+    }
+
+    // Deliver all pending output, or report the size needed and keep it
+    // queued for a retry with a bigger buffer.
+    let len = state.pending.len();
+    if len > out_len {
+        return abi::needed_size(len);
     }
+    std::ptr::copy_nonoverlapping(state.pending.as_ptr(), out_ptr, len);
+    state.pending.clear();
+    if state.done {
+        let _ = handles.remove::<GzipCompressorState>(handle);
+    }
+    len as isize
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn destroy_gzip_compressor(handle: u32) {
-    COMPRESSORS.lock().unwrap().remove(&handle);
+    let _ = HANDLES.lock().unwrap().remove::<GzipCompressorState>(handle);
 }
 
 // ============================================================================
@@ -268,24 +545,86 @@ pub unsafe extern "C" fn destroy_gzip_compressor(handle: u32) {
 // ============================================================================
 
 #[no_mangle]
+#[allow(clippy::too_many_arguments)]
 pub unsafe extern "C" fn decompress_gzip(
     in_ptr: *const u8,
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
+    format: u8,
+    multi_member: u8,
+    dict_ptr: *const u8,
+    dict_len: usize,
+    max_output_size: usize,
+    max_ratio: u32,
 ) -> isize {
     let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let mut decoder = flate2::read::GzDecoder::new(input);
-    let mut output = Vec::new();
-    
-    match decoder.read_to_end(&mut output) {
-        Ok(_) => {
+    let Some(gzip) = decompress_options(format, multi_member, dict_ptr, dict_len) else {
+        return ErrorCode::Failed.code();
+    };
+    let opts = decompression_options(max_output_size, max_ratio);
+
+    let result = GzipDecompressor::with_options(opts, gzip)
+        .and_then(|mut decoder| decoder.decompress_chunk(input, true));
+    match result {
+        Ok(output) => {
             if output.len() > out_len {
-                return -(output.len() as isize);
+                return abi::needed_size(output.len());
             }
             std::ptr::copy_nonoverlapping(output.as_ptr(), out_ptr, output.len());
             output.len() as isize
         }
-        Err(_) => -1,
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
+    }
+}
+
+/// Parse the gzip header at the start of `in_ptr` and copy it into `out_ptr`.
+/// Fails if the input doesn't start with a complete, valid header.
+#[no_mangle]
+pub unsafe extern "C" fn read_gzip_header(
+    in_ptr: *const u8,
+    in_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    let input = std::slice::from_raw_parts(in_ptr, in_len);
+    match GzipHeader::parse(input) {
+        Ok(Some((header, _))) => write_header(&header, out_ptr, out_len),
+        Ok(None) => abi::fail(ErrorCode::TruncatedInput, "Incomplete gzip header"),
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
+    }
+}
+
+// ============================================================================
+// BGZF Random Access
+// ============================================================================
+
+/// Read `out_len` bytes of a complete BGZF file, starting at the virtual
+/// offset made of `block_offset` (the compressed offset of a block) and
+/// `within_block` (an offset into that block's output). Returns the number
+/// of bytes read, which is less than `out_len` only at the end of the file,
+/// or an error code. `within_block` must fit in 16 bits.
+#[no_mangle]
+pub unsafe extern "C" fn read_bgzf(
+    in_ptr: *const u8,
+    in_len: usize,
+    block_offset: usize,
+    within_block: u32,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    if within_block > 0xffff {
+        return abi::fail(
+            ErrorCode::Failed,
+            format_args!("BGZF offset within block {} exceeds 65535", within_block),
+        );
+    }
+    let input = std::slice::from_raw_parts(in_ptr, in_len);
+    let output = std::slice::from_raw_parts_mut(out_ptr, out_len);
+    let mut reader = BgzfReader::new(input);
+    let virtual_offset = ((block_offset as u64) << 16) | within_block as u64;
+    match reader.seek(virtual_offset).and_then(|_| reader.read(output)) {
+        Ok(n) => n as isize,
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
     }
 }
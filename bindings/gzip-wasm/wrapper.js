@@ -1,7 +1,4 @@
-import { 
-  compress_level_1, 
-  compress_level_6, 
-  compress_level_9,
+import {
   decompress_gzip,
   create_gzip_compressor,
   compress_gzip_chunk,
@@ -13,6 +10,127 @@ import {
   ensureReady
 } from './core.js';
 
+// Flush modes understood by compress_gzip_chunk
+const FLUSH_NONE = 0;
+const FLUSH_FINISH = 1;
+const FLUSH_SYNC = 2;
+const FLUSH_FULL = 3;
+
+// Containers understood by the create/decompress exports
+const FORMATS = { gzip: 0, zlib: 1, raw: 2, bgzf: 3 };
+
+function formatCode(format = 'gzip') {
+  const code = FORMATS[format];
+  if (code === undefined) {
+    throw new Error(`Unknown format "${format}" (expected gzip, zlib, raw or bgzf)`);
+  }
+  return code;
+}
+
+// Formats made of gzip members, which carry headers and member boundaries
+function hasGzipMembers(format) {
+  return format === FORMATS.gzip || format === FORMATS.bgzf;
+}
+
+// Gzip header fields set through compress options
+const HEADER_FIELDS = ['filename', 'mtime', 'comment', 'extra', 'os'];
+const OS_UNKNOWN = 255;
+
+function hasHeaderOptions(options) {
+  return HEADER_FIELDS.some((field) => options[field] !== undefined);
+}
+
+const textEncoder = new TextEncoder();
+const textDecoder = new TextDecoder();
+
+function toMtime(mtime) {
+  if (mtime === undefined) return 0;
+  if (mtime instanceof Date) return Math.floor(mtime.getTime() / 1000);
+  return mtime;
+}
+
+/**
+ * Decode a gzip header as written by the header exports (no FHCRC).
+ * Filename and comment are decoded as UTF-8.
+ */
+function decodeHeader(bytes) {
+  const flags = bytes[3];
+  const header = {
+    filename: undefined,
+    mtime: (bytes[4] | (bytes[5] << 8) | (bytes[6] << 16) | (bytes[7] << 24)) >>> 0,
+    comment: undefined,
+    extra: undefined,
+    os: bytes[9],
+  };
+  let pos = 10;
+  if (flags & 0x04) {
+    const len = bytes[pos] | (bytes[pos + 1] << 8);
+    header.extra = bytes.slice(pos + 2, pos + 2 + len);
+    pos += 2 + len;
+  }
+  const readString = () => {
+    const end = bytes.indexOf(0, pos);
+    const value = textDecoder.decode(bytes.subarray(pos, end));
+    pos = end + 1;
+    return value;
+  };
+  if (flags & 0x08) header.filename = readString();
+  if (flags & 0x10) header.comment = readString();
+  return header;
+}
+
+/**
+ * Call a header export that writes into an output buffer, retrying once with
+ * the size it asks for. Returns the decoded header, or null when the export
+ * returns 0 (not available yet) or an error code.
+ */
+function collectHeader(call) {
+  let outLen = 256;
+  let outPtr = alloc(outLen);
+  try {
+    let written = call(outPtr, outLen);
+    if (written < LOWEST_ERROR_CODE) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = call(outPtr, outLen);
+    }
+    if (written <= 0) return null;
+    return decodeHeader(memoryU8().slice(outPtr, outPtr + written));
+  } finally {
+    free(outPtr, outLen);
+  }
+}
+
+// Bytes per record written by gzip_decompressor_members
+const MEMBER_RECORD_LEN = 32;
+
+function decodeMembers(bytes) {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  const members = [];
+  for (let pos = 0; pos < bytes.byteLength; pos += MEMBER_RECORD_LEN) {
+    const field = (i) => Number(view.getBigUint64(pos + i * 8, true));
+    members.push({
+      compressedOffset: field(0),
+      compressedLength: field(1),
+      outputOffset: field(2),
+      outputLength: field(3),
+    });
+  }
+  return members;
+}
+
+// Deflate strategies understood by the create exports (zlib's numbering)
+const STRATEGIES = { default: 0, filtered: 1, huffmanOnly: 2, rle: 3, fixed: 4 };
+
+function strategyCode(strategy = 'default') {
+  const code = STRATEGIES[strategy];
+  if (code === undefined) {
+    throw new Error(`Unknown strategy "${strategy}" (expected default, filtered, huffmanOnly, rle or fixed)`);
+  }
+  return code;
+}
+
 function toBytes(input) {
   if (input instanceof Uint8Array) return input;
   if (ArrayBuffer.isView(input)) return new Uint8Array(input.buffer, input.byteOffset, input.byteLength);
@@ -20,18 +138,134 @@ function toBytes(input) {
   throw new TypeError("Expected a TypedArray or ArrayBuffer");
 }
 
+// Copy an optional byte field (string, TypedArray or ArrayBuffer) into wasm
+// memory; absent fields are passed as null pointers.
+function copyField(value) {
+  if (value === undefined || value === null) return { ptr: 0, len: 0 };
+  const bytes = typeof value === 'string' ? textEncoder.encode(value) : toBytes(value);
+  const ptr = alloc(Math.max(bytes.byteLength, 1));
+  memoryU8().set(bytes, ptr);
+  return { ptr, len: bytes.byteLength };
+}
+
+function freeField({ ptr, len }) {
+  if (ptr !== 0) free(ptr, Math.max(len, 1));
+}
+
+// Error codes returned by the exports (`core_api::abi::ErrorCode`); any
+// other negative value is the output size needed.
+const ERROR_CODES = {
+  [-1]: 'FAILED',
+  [-2]: 'LIMIT_EXCEEDED',
+  [-3]: 'INVALID_HANDLE',
+  [-4]: 'CORRUPT_DATA',
+  [-5]: 'CHECKSUM_MISMATCH',
+  [-6]: 'TRUNCATED_INPUT',
+  [-7]: 'ALREADY_FINISHED',
+  [-8]: 'OUT_OF_MEMORY',
+};
+const LOWEST_ERROR_CODE = -8;
+
+function isErrorCode(value) {
+  return value < 0 && value >= LOWEST_ERROR_CODE;
+}
+
+/**
+ * Error thrown when a call into the wasm module fails. `code` is one of the
+ * names in ERROR_CODES, e.g. 'CORRUPT_DATA' or 'LIMIT_EXCEEDED'.
+ */
+export class CodecError extends Error {
+  constructor(message, code) {
+    super(message);
+    this.name = 'CodecError';
+    this.code = code;
+  }
+}
+
+// The module's description of its last failure.
+function lastErrorMessage() {
+  let outLen = 256;
+  let outPtr = alloc(outLen);
+  try {
+    let written = wasmExports().last_error_message(outPtr, outLen);
+    if (written < 0) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = wasmExports().last_error_message(outPtr, outLen);
+    }
+    return new TextDecoder().decode(memoryU8().subarray(outPtr, outPtr + Math.max(written, 0)));
+  } finally {
+    free(outPtr, outLen);
+  }
+}
+
+// Error for an export that returned `code`; create exports, which return 0,
+// leave it to the module's last error code.
+function exportError(context, code = wasmExports().last_error_code()) {
+  return new CodecError(`${context}: ${lastErrorMessage()}`, ERROR_CODES[code] ?? 'FAILED');
+}
+
+// Error for a call on a stream that was finished or destroyed in JS already.
+// A chunk export that fails releases the stream's handle, for compressors and
+// decompressors alike, so a stream is closed after any error and later calls
+// get this error too.
+function closedError(kind, finished) {
+  return finished
+    ? new CodecError(`${kind} already finished`, 'ALREADY_FINISHED')
+    : new CodecError(`${kind} already destroyed`, 'INVALID_HANDLE');
+}
+
 // ============================================================================
 // One-shot Compression API
 // ============================================================================
 
+// Compress with the one-shot export, growing the output buffer once if the
+// result doesn't fit.
+function compressOneShot(input, level) {
+  const view = toBytes(input);
+  const len = view.byteLength;
+  let outLen = len + 1024;
+  const inPtr = alloc(len);
+  let outPtr = alloc(outLen);
+  try {
+    memoryU8().set(view, inPtr);
+    let written = wasmExports().compress_gzip(inPtr, len, outPtr, outLen, level);
+    if (written < LOWEST_ERROR_CODE) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = wasmExports().compress_gzip(inPtr, len, outPtr, outLen, level);
+    }
+    if (written < 0) {
+      throw exportError('Compression failed', written);
+    }
+    return memoryU8().slice(outPtr, outPtr + written);
+  } finally {
+    free(inPtr, len);
+    free(outPtr, outLen);
+  }
+}
+
 export async function compress(input, options = {}) {
   const level = options.level ?? 6;
   
   try {
-    if (level <= 1) return compress_level_1(input);
-    if (level <= 6) return compress_level_6(input);
-    return compress_level_9(input);
+    if (
+      formatCode(options.format) !== FORMATS.gzip ||
+      strategyCode(options.strategy) !== STRATEGIES.default ||
+      hasHeaderOptions(options) ||
+      options.dictionary !== undefined
+    ) {
+      // The one-shot exports write plain gzip; other containers, strategies,
+      // header fields and dictionaries use a single-use handle.
+      const enc = new StreamingCompressor(options);
+      return await enc.compressChunk(input, true);
+    }
+    await ensureReady();
+    return compressOneShot(input, level);
   } catch (error) {
+    if (error.code) throw error;
     throw new Error(`Compression failed: ${error.message}`);
   }
 }
@@ -40,23 +274,59 @@ export async function compress(input, options = {}) {
 // One-shot Decompression API
 // ============================================================================
 
-export async function decompress(input) {
+/**
+ * Read the header of a gzip stream without decompressing it.
+ * @param {Uint8Array} input gzip data, at least up to the end of the header
+ * @returns {Promise<{ filename?: string, mtime: number, comment?: string, extra?: Uint8Array, os: number }>}
+ */
+export async function readHeader(input) {
+  await ensureReady();
+
+  const view = toBytes(input);
+  const len = view.byteLength;
+  const inPtr = alloc(len);
+  try {
+    memoryU8().set(view, inPtr);
+    const header = collectHeader((outPtr, outLen) =>
+      wasmExports().read_gzip_header(inPtr, len, outPtr, outLen)
+    );
+    if (header === null) {
+      throw exportError('Invalid gzip header');
+    }
+    return header;
+  } finally {
+    free(inPtr, len);
+  }
+}
+
+export async function decompress(input, options = {}) {
   await ensureReady();
   
+  const format = formatCode(options.format);
+  const multiMember = options.multiMember === false ? 0 : 1;
   const view = toBytes(input);
   const len = view.byteLength;
+  const maxOutputSize = options.maxOutputSize ?? 0;
+  const maxRatio = options.maxRatio ?? 0;
   
   // Start with a reasonable estimate (10x compressed size)
   let outLen = Math.max(len * 10, 65536);
   
   const inPtr = alloc(len);
   let outPtr = alloc(outLen);
+  const dict = copyField(options.dictionary);
+  const run = () => wasmExports().decompress_gzip(
+    inPtr, len, outPtr, outLen, format, multiMember, dict.ptr, dict.len, maxOutputSize, maxRatio
+  );
   
   try {
     memoryU8().set(view, inPtr);
-    let written = wasmExports().decompress_gzip(inPtr, len, outPtr, outLen);
+    let written = run();
+    if (isErrorCode(written)) {
+      throw exportError('Decompression failed', written);
+    }
     
-    // Negative value means we need more space
+    // Any other negative value means we need more space
     if (written < 0) {
       const neededLen = -written;
       free(outPtr, outLen);
@@ -64,24 +334,86 @@ export async function decompress(input) {
       outPtr = alloc(outLen);
       
       // Retry with correct size
-      written = wasmExports().decompress_gzip(inPtr, len, outPtr, outLen);
+      written = run();
       
       if (written < 0) {
-        throw new Error('Decompression failed after resize');
+        throw exportError('Decompression failed', written);
       }
     }
     
     const result = memoryU8().slice(outPtr, outPtr + written);
     free(inPtr, len);
     free(outPtr, outLen);
+    freeField(dict);
     return result;
   } catch (error) {
     free(inPtr, len);
     free(outPtr, outLen);
+    freeField(dict);
+    if (error.code) throw error;
     throw new Error(`Decompression failed: ${error.message}`);
   }
 }
 
+/**
+ * Decompress a complete stream and report where each gzip member starts and
+ * ends, in the compressed input and in the output.
+ * @param {Uint8Array} input
+ * @param {{ format?: 'gzip' | 'zlib' | 'raw' | 'bgzf', multiMember?: boolean, dictionary?: Uint8Array, maxOutputSize?: number, maxRatio?: number }} [options]
+ * @returns {Promise<{ data: Uint8Array, members: Array<{ compressedOffset: number, compressedLength: number, outputOffset: number, outputLength: number }> }>}
+ */
+export async function decompressMembers(input, options = {}) {
+  // Member boundaries are only exposed on handles, so use a single-use one.
+  const dec = new StreamingDecompressor(options);
+  try {
+    const chunks = [await dec.decompressChunk(input, true)];
+    while (chunks[chunks.length - 1].length) {
+      chunks.push(await dec.decompressChunk(new Uint8Array(0), false));
+    }
+    const data = new Uint8Array(chunks.reduce((sum, chunk) => sum + chunk.length, 0));
+    let offset = 0;
+    for (const chunk of chunks) {
+      data.set(chunk, offset);
+      offset += chunk.length;
+    }
+    return { data, members: dec.members };
+  } finally {
+    await dec.destroy();
+  }
+}
+
+/**
+ * Read bytes from a complete BGZF file (BAM, `.vcf.gz`) starting at a virtual
+ * offset, as found in BAI/tabix indexes. Only the blocks covering the range
+ * are decompressed.
+ * @param {Uint8Array} input the whole BGZF file
+ * @param {bigint | number} virtualOffset compressed block offset << 16 | offset within the block
+ * @param {number} length bytes to read; fewer are returned at the end of the file
+ * @returns {Promise<Uint8Array>}
+ */
+export async function readBgzf(input, virtualOffset, length) {
+  await ensureReady();
+
+  const offset = BigInt(virtualOffset);
+  const blockOffset = Number(offset >> 16n);
+  const withinBlock = Number(offset & 0xffffn);
+  const view = toBytes(input);
+  const len = view.byteLength;
+  const inPtr = alloc(len);
+  const outPtr = alloc(length);
+  try {
+    memoryU8().set(view, inPtr);
+    const read = wasmExports().read_bgzf(inPtr, len, blockOffset, withinBlock, outPtr, length);
+    if (read < 0) {
+      throw exportError('Invalid BGZF data or virtual offset', read);
+    }
+    return memoryU8().slice(outPtr, outPtr + read);
+  } finally {
+    free(inPtr, len);
+    free(outPtr, length);
+  }
+}
+
 // ============================================================================
 // Streaming Compression API
 // ============================================================================
@@ -90,23 +422,82 @@ export class StreamingCompressor {
   constructor(options = {}) {
     this._initPromise = ensureReady();
     this.level = options.level ?? 6;
+    this.format = formatCode(options.format);
+    this.strategy = strategyCode(options.strategy);
+    this.header = hasHeaderOptions(options) ? options : null;
+    if (this.header && this.format !== FORMATS.gzip) {
+      throw new Error('Header fields are only supported by the gzip format');
+    }
+    this.dictionary = options.dictionary;
+    if (this.dictionary !== undefined && hasGzipMembers(this.format)) {
+      throw new Error('Preset dictionaries are only supported by the zlib and raw formats');
+    }
     this.handle = null;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
   }
 
   async _ensureInit() {
     await this._initPromise;
     if (this.handle === null) {
-      this.handle = wasmExports().create_gzip_compressor(this.level);
+      this.handle = this.header
+        ? this._createWithHeader(this.header)
+        : this._create();
       if (this.handle === 0) {
-        throw new Error('Failed to create compressor');
+        throw exportError('Failed to create compressor');
       }
     }
   }
 
+  _create() {
+    const dict = copyField(this.dictionary);
+    try {
+      return wasmExports().create_gzip_compressor(
+        this.level, this.format, this.strategy, dict.ptr, dict.len
+      );
+    } finally {
+      freeField(dict);
+    }
+  }
+
+  _createWithHeader({ filename, mtime, comment, extra, os }) {
+    const fields = [filename, comment, extra].map(copyField);
+    try {
+      const [name, note, extraField] = fields;
+      return wasmExports().create_gzip_compressor_with_header(
+        this.level,
+        this.strategy,
+        toMtime(mtime),
+        os ?? OS_UNKNOWN,
+        name.ptr, name.len,
+        note.ptr, note.len,
+        extraField.ptr, extraField.len
+      );
+    } finally {
+      fields.forEach(freeField);
+    }
+  }
+
   async compressChunk(input, finish = false) {
+    return this._compressChunk(input, finish ? FLUSH_FINISH : FLUSH_NONE);
+  }
+
+  /**
+   * Compress `input` and force out everything written so far, so a receiver
+   * can decode it, without ending the stream.
+   * @param {Uint8Array} [input]
+   * @param {{ full?: boolean }} [options] full: also reset the compression history so a receiver can resync
+   * @returns {Promise<Uint8Array>}
+   */
+  async flush(input = new Uint8Array(0), options = {}) {
+    return this._compressChunk(input, options.full ? FLUSH_FULL : FLUSH_SYNC);
+  }
+
+  async _compressChunk(input, mode) {
+    const finish = mode === FLUSH_FINISH;
     await this._ensureInit();
     if (this.handle === 0) {
-      throw new Error('Compressor already destroyed');
+      throw closedError('Compressor', this.finished);
     }
     
     const view = toBytes(input);
@@ -118,26 +509,32 @@ export class StreamingCompressor {
     
     try {
       memoryU8().set(view, inPtr);
-      const written = wasmExports().compress_gzip_chunk(this.handle, inPtr, len, outPtr, outLen, finish ? 1 : 0);
+      const written = wasmExports().compress_gzip_chunk(this.handle, inPtr, len, outPtr, outLen, mode);
       
       if (written < 0) {
-        if (written === -1) {
-          throw new Error('Compression failed');
+        if (isErrorCode(written)) {
+          this.handle = 0;
+          throw exportError('Compression failed', written);
         } else {
           // Negative value indicates needed buffer size
           free(outPtr, outLen);
           const neededLen = -written;
           const newOutPtr = alloc(neededLen);
-          memoryU8().set(view, inPtr);
-          const retryWritten = wasmExports().compress_gzip_chunk(this.handle, inPtr, len, newOutPtr, neededLen, finish ? 1 : 0);
+          // The output is queued on the Rust side; collect it without
+          // feeding the input again.
+          const retryWritten = wasmExports().compress_gzip_chunk(this.handle, inPtr, 0, newOutPtr, neededLen, FLUSH_NONE);
           if (retryWritten < 0) {
             free(newOutPtr, neededLen);
-            throw new Error('Compression failed after retry');
+            this.handle = 0;
+            throw exportError('Compression failed after retry', retryWritten);
           }
           const result = memoryU8().slice(newOutPtr, newOutPtr + retryWritten);
           free(newOutPtr, neededLen);
           free(inPtr, len);
-          if (finish) this.handle = 0;
+          if (finish) {
+            this.handle = 0;
+            this.finished = true;
+          }
           return result;
         }
       }
@@ -152,11 +549,15 @@ export class StreamingCompressor {
       free(outPtr, outLen);
       free(inPtr, len);
       
-      if (finish) this.handle = 0;
+      if (finish) {
+        this.handle = 0;
+        this.finished = true;
+      }
       return result;
     } catch (error) {
       free(outPtr, outLen);
       free(inPtr, len);
+      if (error.code) throw error;
       throw new Error(`Compression failed: ${error.message}`);
     }
   }
@@ -175,17 +576,70 @@ export class StreamingCompressor {
 // ============================================================================
 
 export class StreamingDecompressor {
-  constructor() {
+  constructor(options = {}) {
     this._initPromise = ensureReady();
+    this.maxOutputSize = options.maxOutputSize ?? 0;
+    this.maxRatio = options.maxRatio ?? 0;
+    this.format = formatCode(options.format);
+    this.multiMember = options.multiMember === false ? 0 : 1;
+    this.dictionary = options.dictionary;
+    // Gzip header of the first member, set once the decompressor has received it.
+    this.header = null;
+    // Boundaries of the gzip members completed so far.
+    this.members = [];
     this.handle = null;
+    // Set once a call has passed finish; the handle is released when the
+    // output after it has been drained.
+    this.finishing = false;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
+  }
+
+  _readHeader() {
+    if (this.header !== null || !hasGzipMembers(this.format)) return;
+    this.header = collectHeader((outPtr, outLen) =>
+      wasmExports().gzip_decompressor_header(this.handle, outPtr, outLen)
+    );
+  }
+
+  _readMembers() {
+    if (!hasGzipMembers(this.format)) return;
+    let outLen = MEMBER_RECORD_LEN * 4;
+    let outPtr = alloc(outLen);
+    try {
+      let written = wasmExports().gzip_decompressor_members(this.handle, outPtr, outLen);
+      if (written < LOWEST_ERROR_CODE) {
+        free(outPtr, outLen);
+        outLen = -written;
+        outPtr = alloc(outLen);
+        written = wasmExports().gzip_decompressor_members(this.handle, outPtr, outLen);
+      }
+      if (written > 0) {
+        this.members.push(...decodeMembers(memoryU8().slice(outPtr, outPtr + written)));
+      }
+    } finally {
+      free(outPtr, outLen);
+    }
   }
 
   async _ensureInit() {
     await this._initPromise;
     if (this.handle === null) {
-      this.handle = wasmExports().create_gzip_decompressor();
+      const dict = copyField(this.dictionary);
+      try {
+        this.handle = wasmExports().create_gzip_decompressor(
+          this.format,
+          this.multiMember,
+          dict.ptr,
+          dict.len,
+          this.maxOutputSize,
+          this.maxRatio
+        );
+      } finally {
+        freeField(dict);
+      }
       if (this.handle === 0) {
-        throw new Error('Failed to create decompressor');
+        throw exportError('Failed to create decompressor');
       }
     }
   }
@@ -193,7 +647,7 @@ export class StreamingDecompressor {
   async decompressChunk(input, finish = false) {
     await this._ensureInit();
     if (this.handle === 0) {
-      throw new Error('Decompressor already destroyed');
+      throw closedError('Decompressor', this.finished);
     }
 
     const view = toBytes(input);
@@ -217,25 +671,37 @@ export class StreamingDecompressor {
       );
 
       if (written < 0) {
-        throw new Error('Decompression failed');
+        // The Rust side has already released the handle.
+        this.handle = 0;
+        throw exportError('Decompression failed', written);
       }
+      if (finish) this.finishing = true;
+      this._readHeader();
+      this._readMembers();
 
       if (written === 0) {
         free(inPtr, len);
         free(outPtr, outLen);
-        if (finish) this.handle = 0;
+        if (this.finishing) {
+          // Everything is out and the header and members are collected; the
+          // Rust side leaves releasing a finished stream to us.
+          wasmExports().destroy_gzip_decompressor(this.handle);
+          this.handle = 0;
+          this.finished = true;
+        }
         return new Uint8Array(0);
       }
 
+      // Output may still be pending on the Rust side after finish; the handle
+      // is released once a call returns 0, so keep it until then.
       const result = memoryU8().slice(outPtr, outPtr + written);
       free(inPtr, len);
       free(outPtr, outLen);
-
-      if (finish) this.handle = 0;
       return result;
     } catch (error) {
       free(inPtr, len);
       free(outPtr, outLen);
+      if (error.code) throw error;
       throw new Error(`Decompression failed: ${error.message}`);
     }
   }
@@ -245,6 +711,7 @@ export class StreamingDecompressor {
     if (this.handle !== 0 && this.handle !== null) {
       wasmExports().destroy_gzip_decompressor(this.handle);
       this.handle = 0;
+      this.finished = true;
     }
   }
 }
@@ -262,7 +729,7 @@ function requireTransformStream() {
 /**
  * Create a TransformStream that gzip-compresses a byte stream.
  *
- * @param {{ level?: number }} [options]
+ * @param {{ level?: number, format?: 'gzip' | 'zlib' | 'raw' | 'bgzf', strategy?: 'default' | 'filtered' | 'huffmanOnly' | 'rle' | 'fixed', filename?: string, mtime?: number | Date, comment?: string, extra?: Uint8Array, os?: number, dictionary?: Uint8Array }} [options]
  * @returns {TransformStream<Uint8Array, Uint8Array>}
  */
 export function createCompressionStream(options = {}) {
@@ -284,11 +751,12 @@ export function createCompressionStream(options = {}) {
 /**
  * Create a TransformStream that gzip-decompresses a byte stream.
  *
+ * @param {{ format?: 'gzip' | 'zlib' | 'raw' | 'bgzf', multiMember?: boolean, dictionary?: Uint8Array, maxOutputSize?: number, maxRatio?: number }} [options]
  * @returns {TransformStream<Uint8Array, Uint8Array>}
  */
-export function createDecompressionStream() {
+export function createDecompressionStream(options = {}) {
   requireTransformStream();
-  const dec = new StreamingDecompressor();
+  const dec = new StreamingDecompressor(options);
 
   async function drain(controller, finish) {
     // Drain any remaining output buffered on the Rust side.
@@ -317,7 +785,7 @@ export function createDecompressionStream() {
 /**
  * Convenience helper: readable.pipeThrough(createCompressionStream()).
  * @param {ReadableStream<Uint8Array>} readable
- * @param {{ level?: number }} [options]
+ * @param {{ level?: number, format?: 'gzip' | 'zlib' | 'raw' | 'bgzf', strategy?: 'default' | 'filtered' | 'huffmanOnly' | 'rle' | 'fixed', dictionary?: Uint8Array }} [options]
  */
 export function compressStream(readable, options = {}) {
   return readable.pipeThrough(createCompressionStream(options));
@@ -326,9 +794,10 @@ export function compressStream(readable, options = {}) {
 /**
  * Convenience helper: readable.pipeThrough(createDecompressionStream()).
  * @param {ReadableStream<Uint8Array>} readable
+ * @param {{ format?: 'gzip' | 'zlib' | 'raw' | 'bgzf', multiMember?: boolean, dictionary?: Uint8Array, maxOutputSize?: number, maxRatio?: number }} [options]
  */
-export function decompressStream(readable) {
-  return readable.pipeThrough(createDecompressionStream());
+export function decompressStream(readable, options = {}) {
+  return readable.pipeThrough(createDecompressionStream(options));
 }
 
 export { wasmExports };
@@ -1,9 +1,14 @@
-use codec_lz4::Lz4Compressor;
-use core_api::{CompressionOptions, Compressor, Flush};
-use std::collections::HashMap;
-use std::io::Read;
+use codec_lz4::{
+    Lz4BlockSize, Lz4Compressor, Lz4DecompressOptions, Lz4Decompressor, Lz4Error,
+    Lz4FrameDescriptor, Lz4FrameOptions,
+};
+use core_api::abi::{self, ErrorCode};
+use core_api::handles::{HandleKind, HandleRegistry};
+use core_api::{
+    CompressionOptions, Compressor, DecompressionOptions, Decompressor, ErrorKind, Flush,
+    HasErrorKind,
+};
 use std::sync::Mutex;
-use std::sync::LazyLock;
 
 // --- wasm-bindgen-lite bindings ---
 
@@ -21,58 +26,265 @@ pub unsafe extern "C" fn free_bytes(ptr: *mut u8, len: usize) {
     dealloc(ptr, layout);
 }
 
-// For streaming decompression, we accumulate compressed data in a buffer
+/// Code of the last failed call (see `core_api::abi::ErrorCode`).
+#[no_mangle]
+pub extern "C" fn last_error_code() -> i32 {
+    abi::last_error_code() as i32
+}
+
+/// Copy the UTF-8 description of the last failed call into `out_ptr`.
+/// Returns its length, or the negative size needed.
+#[no_mangle]
+pub unsafe extern "C" fn last_error_message(out_ptr: *mut u8, out_len: usize) -> isize {
+    let message = abi::last_error_message();
+    if message.len() > out_len {
+        return abi::needed_size(message.len());
+    }
+    std::ptr::copy_nonoverlapping(message.as_ptr(), out_ptr, message.len());
+    message.len() as isize
+}
+
+struct CompressorState {
+    compressor: Lz4Compressor,
+    // Compressed bytes that didn't fit the caller's buffer, delivered on the next call
+    pending: Vec<u8>,
+    done: bool,
+}
+
 struct DecompressorState {
-    buffer: Vec<u8>,
+    decoder: Lz4Decompressor,
+    // Pending decompressed bytes not yet returned to JS
+    pending: Vec<u8>,
+    pending_offset: usize,
+    done: bool,
 }
 
-// Static storage for streaming compressors and decompressors
-static COMPRESSORS: LazyLock<Mutex<HashMap<u32, Lz4Compressor>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
-static DECOMPRESSORS: LazyLock<Mutex<HashMap<u32, DecompressorState>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
-static mut HANDLE_COUNTER: u32 = 1;
+// Static storage for streaming compressors and decompressors, and for
+// dictionaries registered once and referenced by handle from every call
+core_api::handle_entries! {
+    enum Entry {
+        Compressor(CompressorState),
+        Decompressor(DecompressorState),
+        Dictionary(Vec<u8>),
+    }
+}
 
-fn next_handle() -> u32 {
-    unsafe {
-        let handle = HANDLE_COUNTER;
-        HANDLE_COUNTER += 1;
-        handle
+static HANDLES: Mutex<HandleRegistry<Entry>> = Mutex::new(HandleRegistry::new());
+
+/// Store a new object and return its handle, or 0 when every handle is taken.
+fn insert_handle<K: HandleKind<Entry>>(value: K) -> u32 {
+    HANDLES.lock().unwrap().insert(value).unwrap_or_else(|| {
+        abi::fail(ErrorCode::Failed, "Too many open handles");
+        0
+    })
+}
+
+/// Record a codec failure for `last_error_message` and return its ABI code;
+/// `fallback` is the code for io errors the codec can't classify further.
+fn codec_error(e: Lz4Error, fallback: ErrorCode) -> isize {
+    let code = match e.kind() {
+        ErrorKind::Io => fallback,
+        kind => ErrorCode::from(kind),
+    };
+    abi::fail(code, e)
+}
+
+/// Queue `out` behind the output JS hasn't collected yet, failing rather
+/// than aborting when memory runs out.
+fn queue_output(pending: &mut Vec<u8>, out: &[u8]) -> Result<(), isize> {
+    if pending.try_reserve(out.len()).is_err() {
+        return Err(abi::fail(ErrorCode::OutOfMemory, "Out of memory for pending output"));
+    }
+    pending.extend_from_slice(out);
+    Ok(())
+}
+
+/// Build decompression limits from the ABI arguments; 0 means unlimited.
+fn decompression_options(max_output_size: usize, max_ratio: u32) -> DecompressionOptions {
+    DecompressionOptions {
+        max_output_size: (max_output_size != 0).then_some(max_output_size as u64),
+        max_ratio: (max_ratio != 0).then_some(max_ratio),
     }
 }
 
-// LZ4 is a single-speed algorithm - no compression levels
+/// Resolve the `dict` argument of an export: 0 means no dictionary, anything
+/// else a handle from `create_lz4_dictionary`. A bad handle is recorded and
+/// its error code returned.
+fn resolve_dictionary(
+    handles: &HandleRegistry<Entry>,
+    dict: u32,
+) -> Result<Option<&[u8]>, isize> {
+    match dict {
+        0 => Ok(None),
+        _ => handles
+            .get::<Vec<u8>>(dict)
+            .map(|d| Some(d.as_slice()))
+            .map_err(|e| abi::fail(ErrorCode::InvalidHandle, e)),
+    }
+}
+
+/// Copy of the dictionary behind `dict`, for a compressor or decompressor to
+/// own.
+fn dictionary_copy(dict: u32) -> Result<Option<Vec<u8>>, isize> {
+    let handles = HANDLES.lock().unwrap();
+    resolve_dictionary(&handles, dict).map(|d| d.map(<[u8]>::to_vec))
+}
+
+/// Build frame settings from the ABI arguments. `block_size` is the block
+/// maximum in bytes (0 = 64 KiB); the flags are 0 or 1. Returns `None`, with
+/// the error recorded, for a size LZ4 frames can't describe.
+fn frame_options(
+    block_size: usize,
+    linked_blocks: u8,
+    block_checksums: u8,
+    content_checksum: u8,
+) -> Option<Lz4FrameOptions> {
+    let block_size = match block_size {
+        0 => Lz4BlockSize::Max64KB,
+        size => match Lz4BlockSize::from_bytes(size) {
+            Ok(block_size) => block_size,
+            Err(e) => {
+                codec_error(e, ErrorCode::Failed);
+                return None;
+            }
+        },
+    };
+    Some(Lz4FrameOptions {
+        block_size,
+        linked_blocks: linked_blocks != 0,
+        block_checksums: block_checksums != 0,
+        content_checksum: content_checksum != 0,
+        content_size: None,
+        dictionary: None,
+    })
+}
+
+/// Copy a frame descriptor to JS as the frame header that encodes it, which
+/// the wrapper decodes; negative = needed size.
+unsafe fn write_descriptor(
+    descriptor: &Lz4FrameDescriptor,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    let bytes = match descriptor.to_bytes() {
+        Ok(bytes) => bytes,
+        Err(e) => return codec_error(e, ErrorCode::Failed),
+    };
+    if bytes.len() > out_len {
+        return abi::needed_size(bytes.len());
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), out_ptr, bytes.len());
+    bytes.len() as isize
+}
+
+/// One-shot LZ4 frame compression. Levels below 3 use the fast encoder,
+/// 3-12 the high-compression (HC) one. With `content_size` set, the input
+/// length is recorded in the frame header. `dict` is a dictionary handle
+/// (0 = none).
 #[no_mangle]
+#[allow(clippy::too_many_arguments)]
 pub unsafe extern "C" fn compress_lz4(
     in_ptr: *const u8,
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
+    level: u32,
+    block_size: usize,
+    linked_blocks: u8,
+    block_checksums: u8,
+    content_checksum: u8,
+    content_size: u8,
+    dict: u32,
 ) -> isize {
     let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let opts = CompressionOptions::default();
-    
-    match <Lz4Compressor as Compressor>::compress_all(input, opts) {
+    let opts = CompressionOptions {
+        level: Some(level),
+        ..Default::default()
+    };
+    let Some(mut frame) =
+        frame_options(block_size, linked_blocks, block_checksums, content_checksum)
+    else {
+        return ErrorCode::Failed.code();
+    };
+    if content_size != 0 {
+        frame.content_size = Some(in_len as u64);
+    }
+    let dictionary = match dictionary_copy(dict) {
+        Ok(dictionary) => dictionary,
+        Err(code) => return code,
+    };
+    frame.dictionary = dictionary;
+
+    let result = Lz4Compressor::with_options(opts, frame)
+        .and_then(|mut compressor| compressor.compress_chunk(input, Flush::Finish));
+    match result {
         Ok(out) => {
             if out.len() > out_len {
-                return -(out.len() as isize);
+                return abi::needed_size(out.len());
             }
             std::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len());
             out.len() as isize
         }
-        Err(_) => -1,
+        Err(e) => codec_error(e, ErrorCode::Failed),
     }
 }
 
 // Streaming compression API
+
+/// Create a streaming compressor. Frame arguments as for `compress_lz4`;
+/// `content_size` is recorded when `has_content_size` is set, and finishing
+/// after a different amount of input fails.
 #[no_mangle]
-pub unsafe extern "C" fn create_compressor() -> u32 {
-    let handle = next_handle();
-    let opts = CompressionOptions::default();
-    match Lz4Compressor::new(opts) {
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn create_compressor(
+    level: u32,
+    block_size: usize,
+    linked_blocks: u8,
+    block_checksums: u8,
+    content_checksum: u8,
+    has_content_size: u8,
+    content_size: usize,
+    dict: u32,
+) -> u32 {
+    let opts = CompressionOptions {
+        level: Some(level),
+        ..Default::default()
+    };
+    let Some(mut frame) =
+        frame_options(block_size, linked_blocks, block_checksums, content_checksum)
+    else {
+        return 0;
+    };
+    frame.content_size = (has_content_size != 0).then_some(content_size as u64);
+    let Ok(dictionary) = dictionary_copy(dict) else {
+        return 0;
+    };
+    frame.dictionary = dictionary;
+
+    match Lz4Compressor::with_options(opts, frame) {
         Ok(compressor) => {
-            COMPRESSORS.lock().unwrap().insert(handle, compressor);
-            handle
+            let state = CompressorState {
+                compressor,
+                pending: Vec::new(),
+                done: false,
+            };
+            insert_handle(state)
+        }
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
         }
-        Err(_) => 0,
+    }
+}
+
+/// Map the `flush` byte of the chunk ABI onto a flush mode:
+/// 0 = none, 1 = finish, 2 = sync, 3 = full.
+fn flush_mode(flush: u8) -> Flush {
+    match flush {
+        0 => Flush::None,
+        2 => Flush::Sync,
+        3 => Flush::Full,
+        _ => Flush::Finish,
     }
 }
 
@@ -83,59 +295,105 @@ pub unsafe extern "C" fn compress_chunk(
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
-    finish: u8,
+    flush: u8,
 ) -> isize {
-    let mut compressors = COMPRESSORS.lock().unwrap();
-    let compressor = match compressors.get_mut(&handle) {
-        Some(c) => c,
-        None => return -1,
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<CompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
     };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<CompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
 
-    let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let flush = if finish != 0 { Flush::Finish } else { Flush::None };
-
-    match compressor.compress_chunk(input, flush) {
-        Ok(out) => {
-            if out.is_empty() {
-                return 0;
-            }
-            if out.len() > out_len {
-                return -(out.len() as isize);
+    // Feed new input (and the flush request) to the compressor, queuing its
+    // output behind anything an earlier call couldn't deliver. A call with no
+    // input and no flush only drains.
+    let flush = flush_mode(flush);
+    if !state.done && (in_len > 0 || flush != Flush::None) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.compressor.compress_chunk(input, flush) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<CompressorState>(handle);
+                    return code;
+                }
             }
-            std::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len());
-            let result = out.len() as isize;
-            if finish != 0 {
-                compressors.remove(&handle);
+            Err(e) => {
+                let _ = handles.remove::<CompressorState>(handle);
+                return codec_error(e, ErrorCode::Failed);
             }
-            result
         }
-        Err(_) => -1,
+        if flush == Flush::Finish {
+            state.done = true;
+        }
+    }
+
+    // Deliver all pending output, or report the size needed and keep it
+    // queued for a retry with a bigger buffer.
+    let len = state.pending.len();
+    if len > out_len {
+        return abi::needed_size(len);
     }
+    std::ptr::copy_nonoverlapping(state.pending.as_ptr(), out_ptr, len);
+    state.pending.clear();
+    if state.done {
+        let _ = handles.remove::<CompressorState>(handle);
+    }
+    len as isize
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn destroy_compressor(handle: u32) {
-    COMPRESSORS.lock().unwrap().remove(&handle);
+    let _ = HANDLES.lock().unwrap().remove::<CompressorState>(handle);
 }
 
 // ============================================================================
 // Block API - Raw LZ4 compression without frame overhead (maximum speed)
 // ============================================================================
 
+/// Register a dictionary for the `dict` argument of the compress and
+/// decompress exports, so it is copied into wasm memory only once.
+/// Returns its handle, or 0 if no handle is free.
+#[no_mangle]
+pub unsafe extern "C" fn create_lz4_dictionary(dict_ptr: *const u8, dict_len: usize) -> u32 {
+    let dictionary = std::slice::from_raw_parts(dict_ptr, dict_len).to_vec();
+    insert_handle(dictionary)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn destroy_lz4_dictionary(handle: u32) {
+    let _ = HANDLES.lock().unwrap().remove::<Vec<u8>>(handle);
+}
+
 /// Compress using raw LZ4 block format (no frame headers, no checksums)
 /// This is the fastest option but output is not compatible with standard LZ4 tools
+/// `dict` is a dictionary handle (0 = none).
 #[no_mangle]
 pub unsafe extern "C" fn compress_lz4_block(
     in_ptr: *const u8,
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
+    dict: u32,
 ) -> isize {
     let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let compressed = lz4_flex::block::compress(input);
+    let handles = HANDLES.lock().unwrap();
+    let dictionary = match resolve_dictionary(&handles, dict) {
+        Ok(dictionary) => dictionary,
+        Err(code) => return code,
+    };
+    let compressed = codec_lz4::compress_block(input, dictionary);
     
     if compressed.len() > out_len {
-        return -(compressed.len() as isize);
+        return abi::needed_size(compressed.len());
     }
     std::ptr::copy_nonoverlapping(compressed.as_ptr(), out_ptr, compressed.len());
     compressed.len() as isize
@@ -143,24 +401,31 @@ pub unsafe extern "C" fn compress_lz4_block(
 
 /// Decompress raw LZ4 block format
 /// Requires knowing the uncompressed size in advance
+/// `dict` is the dictionary handle the block was compressed with (0 = none).
 #[no_mangle]
 pub unsafe extern "C" fn decompress_lz4_block(
     in_ptr: *const u8,
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
+    dict: u32,
 ) -> isize {
     let input = std::slice::from_raw_parts(in_ptr, in_len);
+    let handles = HANDLES.lock().unwrap();
+    let dictionary = match resolve_dictionary(&handles, dict) {
+        Ok(dictionary) => dictionary,
+        Err(code) => return code,
+    };
     
-    match lz4_flex::block::decompress(input, out_len) {
+    match codec_lz4::decompress_block(input, out_len, dictionary) {
         Ok(decompressed) => {
             if decompressed.len() > out_len {
-                return -(decompressed.len() as isize);
+                return abi::needed_size(decompressed.len());
             }
             std::ptr::copy_nonoverlapping(decompressed.as_ptr(), out_ptr, decompressed.len());
             decompressed.len() as isize
         }
-        Err(_) => -1,
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
     }
 }
 
@@ -175,30 +440,57 @@ pub unsafe extern "C" fn decompress_lz4(
     in_len: usize,
     out_ptr: *mut u8,
     out_len: usize,
+    max_output_size: usize,
+    max_ratio: u32,
+    dict: u32,
 ) -> isize {
     let input = std::slice::from_raw_parts(in_ptr, in_len);
-    let mut output = Vec::new();
-    
-    match lz4_flex::frame::FrameDecoder::new(std::io::Cursor::new(input.to_vec())).read_to_end(&mut output) {
-        Ok(_) => {
+    let opts = decompression_options(max_output_size, max_ratio);
+    let dictionary = match dictionary_copy(dict) {
+        Ok(dictionary) => dictionary,
+        Err(code) => return code,
+    };
+    let lz4 = Lz4DecompressOptions { dictionary };
+
+    let result = Lz4Decompressor::with_options(opts, lz4)
+        .and_then(|mut decoder| decoder.decompress_chunk(input, true));
+    match result {
+        Ok(output) => {
             if output.len() > out_len {
-                return -(output.len() as isize);
+                return abi::needed_size(output.len());
             }
             std::ptr::copy_nonoverlapping(output.as_ptr(), out_ptr, output.len());
             output.len() as isize
         }
-        Err(_) => -1,
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn create_decompressor() -> u32 {
-    let handle = next_handle();
-    let state = DecompressorState {
-        buffer: Vec::new(),
+pub unsafe extern "C" fn create_decompressor(
+    max_output_size: usize,
+    max_ratio: u32,
+    dict: u32,
+) -> u32 {
+    let Ok(dictionary) = dictionary_copy(dict) else {
+        return 0;
     };
-    DECOMPRESSORS.lock().unwrap().insert(handle, state);
-    handle
+    let opts = decompression_options(max_output_size, max_ratio);
+    match Lz4Decompressor::with_options(opts, Lz4DecompressOptions { dictionary }) {
+        Ok(decoder) => {
+            let state = DecompressorState {
+                decoder,
+                pending: Vec::new(),
+                pending_offset: 0,
+                done: false,
+            };
+            insert_handle(state)
+        }
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
+        }
+    }
 }
 
 #[no_mangle]
@@ -210,43 +502,103 @@ pub unsafe extern "C" fn decompress_chunk(
     out_len: usize,
     finish: u8,
 ) -> isize {
-    let mut decompressors = DECOMPRESSORS.lock().unwrap();
-    let state = match decompressors.get_mut(&handle) {
-        Some(s) => s,
-        None => return -1,
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<DecompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
     };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<DecompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
 
-    let input = std::slice::from_raw_parts(in_ptr, in_len);
-    state.buffer.extend_from_slice(input);
-
-    // If finish is set, create decoder and read all data
-    if finish != 0 {
-        let mut output = Vec::new();
-        match lz4_flex::frame::FrameDecoder::new(std::io::Cursor::new(state.buffer.clone())).read_to_end(&mut output) {
-            Ok(_) => {
-                if output.len() > out_len {
-                    return -(output.len() as isize);
-                }
-                if !output.is_empty() {
-                    std::ptr::copy_nonoverlapping(output.as_ptr(), out_ptr, output.len());
+    // Feed new compressed input (and the end-of-stream signal) to the decoder,
+    // stashing whatever it produces behind any output JS hasn't collected yet.
+    if !state.done && (in_len > 0 || finish != 0) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.decoder.decompress_chunk(input, finish != 0) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<DecompressorState>(handle);
+                    return code;
                 }
-                let result = output.len() as isize;
-                decompressors.remove(&handle);
-                result
             }
-            Err(_) => {
-                decompressors.remove(&handle);
-                -1
+            Err(e) => {
+                let _ = handles.remove::<DecompressorState>(handle);
+                return codec_error(e, ErrorCode::CorruptData);
             }
         }
-    } else {
-        // For non-finish chunks, we can't produce output until we have a complete frame
-        // LZ4 frame format requires the full frame to decode, so return 0 until finish
-        0
+        if finish != 0 {
+            state.done = true;
+        }
+    }
+
+    // Drain pending output into the caller's buffer.
+    let remaining = state.pending.len() - state.pending_offset;
+    let to_copy = remaining.min(out_len);
+    if to_copy > 0 {
+        std::ptr::copy_nonoverlapping(
+            state.pending.as_ptr().add(state.pending_offset),
+            out_ptr,
+            to_copy,
+        );
+        state.pending_offset += to_copy;
+    }
+    // If fully drained, reset buffer to avoid unbounded growth.
+    if state.pending_offset >= state.pending.len() {
+        state.pending.clear();
+        state.pending_offset = 0;
+        if state.done && to_copy == 0 {
+            let _ = handles.remove::<DecompressorState>(handle);
+        }
+    }
+    to_copy as isize
+}
+
+/// Copy the frame header of a streaming decompressor's current (or last)
+/// frame into `out_ptr`. Returns 0 until a header has been received, or an
+/// error code.
+#[no_mangle]
+pub unsafe extern "C" fn lz4_decompressor_frame_descriptor(
+    handle: u32,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    let handles = HANDLES.lock().unwrap();
+    match handles.get::<DecompressorState>(handle) {
+        Ok(state) => match state.decoder.frame_descriptor() {
+            Some(descriptor) => write_descriptor(descriptor, out_ptr, out_len),
+            None => 0,
+        },
+        Err(e) => abi::fail(ErrorCode::InvalidHandle, e),
+    }
+}
+
+/// Parse the frame header at the start of `in_ptr` and copy it into
+/// `out_ptr`. Fails if the input doesn't start with a complete, valid
+/// header.
+#[no_mangle]
+pub unsafe extern "C" fn read_lz4_frame_descriptor(
+    in_ptr: *const u8,
+    in_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    let input = std::slice::from_raw_parts(in_ptr, in_len);
+    match Lz4FrameDescriptor::parse(input) {
+        Ok(Some((descriptor, _))) => write_descriptor(&descriptor, out_ptr, out_len),
+        Ok(None) => abi::fail(ErrorCode::TruncatedInput, "Incomplete LZ4 frame header"),
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
     }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn destroy_decompressor(handle: u32) {
-    DECOMPRESSORS.lock().unwrap().remove(&handle);
+    let _ = HANDLES.lock().unwrap().remove::<DecompressorState>(handle);
 }
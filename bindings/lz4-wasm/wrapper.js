@@ -1,7 +1,5 @@
 import { 
-  compress_lz4, 
   compress_lz4_block,
-  decompress_lz4,
   decompress_lz4_block,
   wasmExports,
   alloc,
@@ -10,6 +8,12 @@ import {
   ensureReady
 } from './core.js';
 
+// Flush modes understood by compress_chunk
+const FLUSH_NONE = 0;
+const FLUSH_FINISH = 1;
+const FLUSH_SYNC = 2;
+const FLUSH_FULL = 3;
+
 function toBytes(input) {
   if (input instanceof Uint8Array) return input;
   if (ArrayBuffer.isView(input)) return new Uint8Array(input.buffer, input.byteOffset, input.byteLength);
@@ -17,22 +21,167 @@ function toBytes(input) {
   throw new TypeError("Expected a TypedArray or ArrayBuffer");
 }
 
+// Error codes returned by the exports (`core_api::abi::ErrorCode`); any
+// other negative value is the output size needed.
+const ERROR_CODES = {
+  [-1]: 'FAILED',
+  [-2]: 'LIMIT_EXCEEDED',
+  [-3]: 'INVALID_HANDLE',
+  [-4]: 'CORRUPT_DATA',
+  [-5]: 'CHECKSUM_MISMATCH',
+  [-6]: 'TRUNCATED_INPUT',
+  [-7]: 'ALREADY_FINISHED',
+  [-8]: 'OUT_OF_MEMORY',
+};
+const LOWEST_ERROR_CODE = -8;
+
+function isErrorCode(value) {
+  return value < 0 && value >= LOWEST_ERROR_CODE;
+}
+
+/**
+ * Error thrown when a call into the wasm module fails. `code` is one of the
+ * names in ERROR_CODES, e.g. 'CORRUPT_DATA' or 'LIMIT_EXCEEDED'.
+ */
+export class CodecError extends Error {
+  constructor(message, code) {
+    super(message);
+    this.name = 'CodecError';
+    this.code = code;
+  }
+}
+
+// The module's description of its last failure.
+function lastErrorMessage() {
+  let outLen = 256;
+  let outPtr = alloc(outLen);
+  try {
+    let written = wasmExports().last_error_message(outPtr, outLen);
+    if (written < 0) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = wasmExports().last_error_message(outPtr, outLen);
+    }
+    return new TextDecoder().decode(memoryU8().subarray(outPtr, outPtr + Math.max(written, 0)));
+  } finally {
+    free(outPtr, outLen);
+  }
+}
+
+// Error for an export that returned `code`; create exports, which return 0,
+// leave it to the module's last error code.
+function exportError(context, code = wasmExports().last_error_code()) {
+  return new CodecError(`${context}: ${lastErrorMessage()}`, ERROR_CODES[code] ?? 'FAILED');
+}
+
+// Error for a call on a stream that was finished or destroyed in JS already.
+// A chunk export that fails releases the stream's handle, for compressors and
+// decompressors alike, so a stream is closed after any error and later calls
+// get this error too.
+function closedError(kind, finished) {
+  return finished
+    ? new CodecError(`${kind} already finished`, 'ALREADY_FINISHED')
+    : new CodecError(`${kind} already destroyed`, 'INVALID_HANDLE');
+}
+
+// ============================================================================
+// Dictionaries - registered once, reused by every call they are passed to
+// ============================================================================
+
+class Lz4Dictionary {
+  constructor(handle) {
+    this.handle = handle;
+  }
+
+  /** Release the dictionary; calls passing it afterwards fail. */
+  destroy() {
+    if (this.handle !== 0) {
+      wasmExports().destroy_lz4_dictionary(this.handle);
+      this.handle = 0;
+    }
+  }
+}
+
+/**
+ * Register a dictionary with the wasm module. Pass the result as
+ * `options.dictionary` to the block, frame and streaming APIs; the bytes are
+ * copied into wasm memory once rather than for every message.
+ * @param {Uint8Array} bytes - Data the compressed messages may reference
+ * @returns {Promise<Lz4Dictionary>}
+ */
+export async function createDictionary(bytes) {
+  await ensureReady();
+  const view = toBytes(bytes);
+  const len = view.byteLength;
+  const ptr = alloc(len);
+  try {
+    memoryU8().set(view, ptr);
+    return new Lz4Dictionary(wasmExports().create_lz4_dictionary(ptr, len));
+  } finally {
+    free(ptr, len);
+  }
+}
+
+// The `dict` argument of the exports for options.dictionary (0 = none).
+function dictionaryHandle(dictionary) {
+  if (dictionary === undefined) return 0;
+  if (!(dictionary instanceof Lz4Dictionary)) {
+    throw new TypeError('options.dictionary must come from createDictionary()');
+  }
+  if (dictionary.handle === 0) {
+    throw new CodecError('Dictionary already destroyed', 'INVALID_HANDLE');
+  }
+  return dictionary.handle;
+}
+
 // ============================================================================
 // Block API - Maximum speed, no frame overhead
 // Output is NOT compatible with standard LZ4 tools (lz4 CLI, etc.)
 // Use when you control both compression and decompression
 // ============================================================================
 
+function compressBlockWithDict(input, dict) {
+  const view = toBytes(input);
+  const len = view.byteLength;
+  let outLen = len + 1024;
+  const inPtr = alloc(len);
+  let outPtr = alloc(outLen);
+  try {
+    memoryU8().set(view, inPtr);
+    let written = wasmExports().compress_lz4_block(inPtr, len, outPtr, outLen, dict);
+    if (written < LOWEST_ERROR_CODE) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = wasmExports().compress_lz4_block(inPtr, len, outPtr, outLen, dict);
+    }
+    if (written < 0) {
+      throw exportError('Compression failed', written);
+    }
+    return memoryU8().slice(outPtr, outPtr + written);
+  } finally {
+    free(inPtr, len);
+    free(outPtr, outLen);
+  }
+}
+
 /**
  * Compress using raw LZ4 block format (maximum speed)
  * ~5x faster than frame API due to no checksumming overhead
  * @param {Uint8Array} input - Data to compress
+ * @param {{ dictionary?: Lz4Dictionary }} [options] dictionary: from createDictionary()
  * @returns {Promise<Uint8Array>} Compressed data (raw block format)
  */
-export async function compressBlock(input) {
+export async function compressBlock(input, options = {}) {
   try {
-    return compress_lz4_block(input);
+    if (options.dictionary === undefined) {
+      return compress_lz4_block(input);
+    }
+    await ensureReady();
+    return compressBlockWithDict(input, dictionaryHandle(options.dictionary));
   } catch (error) {
+    if (error.code) throw error;
     throw new Error(`Block compression failed: ${error.message}`);
   }
 }
@@ -41,13 +190,15 @@ export async function compressBlock(input) {
  * Decompress raw LZ4 block format
  * @param {Uint8Array} input - Compressed data (raw block format)
  * @param {number} originalSize - Original uncompressed size (REQUIRED)
+ * @param {{ dictionary?: Lz4Dictionary }} [options] dictionary: the one the block was compressed with
  * @returns {Promise<Uint8Array>} Decompressed data
  */
-export async function decompressBlock(input, originalSize) {
+export async function decompressBlock(input, originalSize, options = {}) {
   if (typeof originalSize !== 'number' || originalSize <= 0) {
     throw new Error('decompressBlock requires originalSize parameter');
   }
   await ensureReady();
+  const dict = dictionaryHandle(options.dictionary);
   
   const view = toBytes(input);
   const len = view.byteLength;
@@ -57,10 +208,10 @@ export async function decompressBlock(input, originalSize) {
   
   try {
     memoryU8().set(view, inPtr);
-    const written = wasmExports().decompress_lz4_block(inPtr, len, outPtr, originalSize);
+    const written = wasmExports().decompress_lz4_block(inPtr, len, outPtr, originalSize, dict);
     
     if (written < 0) {
-      throw new Error('Block decompression failed');
+      throw exportError('Block decompression failed', written);
     }
     
     const result = memoryU8().slice(outPtr, outPtr + written);
@@ -81,11 +232,12 @@ export async function decompressBlock(input, originalSize) {
  * Compress with size prefix - ready for network transfer
  * Output includes original size, so decompression doesn't need it separately
  * @param {Uint8Array} input - Data to compress
+ * @param {{ dictionary?: Lz4Dictionary }} [options] see compressBlock
  * @returns {Promise<Uint8Array>} [4-byte size prefix] + [compressed block]
  */
-export async function compressPacked(input) {
+export async function compressPacked(input, options = {}) {
   const view = toBytes(input);
-  const compressed = await compressBlock(view);
+  const compressed = await compressBlock(view, options);
   
   // Prepend 4-byte little-endian size
   const result = new Uint8Array(4 + compressed.length);
@@ -99,9 +251,10 @@ export async function compressPacked(input) {
 /**
  * Decompress packed format (with size prefix)
  * @param {Uint8Array} input - Packed compressed data (from compressPacked)
+ * @param {{ dictionary?: Lz4Dictionary }} [options] see decompressBlock
  * @returns {Promise<Uint8Array>} Decompressed data
  */
-export async function decompressPacked(input) {
+export async function decompressPacked(input, options = {}) {
   const view = toBytes(input);
   if (view.length < 4) {
     throw new Error('Invalid packed data: too short');
@@ -111,7 +264,7 @@ export async function decompressPacked(input) {
   const originalSize = dataView.getUint32(0, true); // little-endian
   const compressed = view.subarray(4);
   
-  return decompressBlock(compressed, originalSize);
+  return decompressBlock(compressed, originalSize, options);
 }
 
 // ============================================================================
@@ -119,16 +272,122 @@ export async function decompressPacked(input) {
 // Includes headers and checksums for integrity
 // ============================================================================
 
+// Block maximum sizes an LZ4 frame can describe.
+const BLOCK_SIZES = [65536, 262144, 1048576, 4194304];
+
+/**
+ * @typedef {Object} FrameOptions
+ * @property {number} [blockSize] block maximum in bytes: 65536 (default), 262144, 1048576 or 4194304
+ * @property {boolean} [linkedBlocks] let blocks reference the 64 KB before them (default: false)
+ * @property {boolean} [blockChecksums] xxHash32 after each block (default: false)
+ * @property {boolean} [contentChecksum] xxHash32 of the whole input at the end (default: false)
+ * @property {Lz4Dictionary} [dictionary] from createDictionary(); its ID goes in the frame header
+ */
+
+// Frame arguments shared by compress_lz4 and create_compressor.
+function frameArgs(options) {
+  const blockSize = options.blockSize ?? 65536;
+  if (!BLOCK_SIZES.includes(blockSize)) {
+    throw new Error(`Unsupported LZ4 block size: ${blockSize}`);
+  }
+  return [
+    blockSize,
+    options.linkedBlocks ? 1 : 0,
+    options.blockChecksums ? 1 : 0,
+    options.contentChecksum ? 1 : 0,
+  ];
+}
+
+// Decode the frame header written by read_lz4_frame_descriptor and
+// lz4_decompressor_frame_descriptor.
+function decodeDescriptor(bytes) {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  const flg = bytes[4];
+  const bd = bytes[5];
+  const descriptor = {
+    blockSize: 2 ** (8 + 2 * ((bd >> 4) & 0x07)),
+    linkedBlocks: (flg & 0x20) === 0,
+    blockChecksums: (flg & 0x10) !== 0,
+    contentChecksum: (flg & 0x04) !== 0,
+  };
+  let offset = 6;
+  if (flg & 0x08) {
+    descriptor.contentSize = Number(view.getBigUint64(offset, true));
+    offset += 8;
+  }
+  if (flg & 0x01) {
+    descriptor.dictId = view.getUint32(offset, true);
+  }
+  return descriptor;
+}
+
+// Run an export that copies a frame header out, growing the buffer once if
+// needed. Returns null when there is no header (yet).
+function collectDescriptor(call) {
+  let outLen = 32;
+  let outPtr = alloc(outLen);
+  try {
+    let written = call(outPtr, outLen);
+    if (written < LOWEST_ERROR_CODE) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = call(outPtr, outLen);
+    }
+    if (written <= 0) return null;
+    return decodeDescriptor(memoryU8().slice(outPtr, outPtr + written));
+  } finally {
+    free(outPtr, outLen);
+  }
+}
+
+function compressOneShot(input, level, options) {
+  const view = toBytes(input);
+  const len = view.byteLength;
+  const frame = frameArgs(options);
+  const contentSize = options.contentSize ? 1 : 0;
+  const dict = dictionaryHandle(options.dictionary);
+  let outLen = len + 1024;
+  const inPtr = alloc(len);
+  let outPtr = alloc(outLen);
+  const run = () => wasmExports().compress_lz4(
+    inPtr, len, outPtr, outLen, level, ...frame, contentSize, dict
+  );
+  try {
+    memoryU8().set(view, inPtr);
+    let written = run();
+    if (written < LOWEST_ERROR_CODE) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = run();
+    }
+    if (written < 0) {
+      throw exportError('Compression failed', written);
+    }
+    return memoryU8().slice(outPtr, outPtr + written);
+  } finally {
+    free(inPtr, len);
+    free(outPtr, outLen);
+  }
+}
+
 /**
  * Compress using standard LZ4 frame format
  * Compatible with lz4 CLI and other standard tools
  * @param {Uint8Array} input - Data to compress
+ * @param {FrameOptions & { level?: number, contentSize?: boolean }} [options]
+ *   level: 0-2 fast (default: 0), 3-12 high compression;
+ *   contentSize: record the input length in the frame header
  * @returns {Promise<Uint8Array>} Compressed data (LZ4 frame format)
  */
-export async function compress(input) {
+export async function compress(input, options = {}) {
+  const level = options.level ?? 0;
   try {
-    return compress_lz4(input);
+    await ensureReady();
+    return compressOneShot(input, level, options);
   } catch (error) {
+    if (error.code) throw error;
     throw new Error(`Compression failed: ${error.message}`);
   }
 }
@@ -137,7 +396,7 @@ export async function compress(input) {
 // 
 // Note: We don't use wasm-bindgen-lite's createTransformStream() helper here because
 // compression/decompression requires stateful streaming:
-// - Compression: Must accumulate input chunks until finish() is called
+// - Compression: Must keep the frame encoder (and its partially filled block) between chunks
 // - Decompression: Must handle partial frames and buffer incomplete data
 // 
 // createTransformStream() is designed for stateless transformations where each chunk
@@ -146,25 +405,60 @@ export async function compress(input) {
 // 2. Maintains state between chunks via handles
 // 3. Manually manages WASM memory allocation/freeing
 export class StreamingCompressor {
-  constructor() {
+  /**
+   * @param {FrameOptions & { level?: number, contentSize?: number }} [options]
+   *   level: 0-2 fast (default: 0), 3-12 high compression;
+   *   contentSize: total input length, recorded in the frame header (finishing
+   *   after a different amount of input fails)
+   */
+  constructor(options = {}) {
     this._initPromise = ensureReady();
+    this.level = options.level ?? 0;
+    this.frame = frameArgs(options);
+    this.contentSize = options.contentSize;
+    this.dictionary = options.dictionary;
     this.handle = null;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
   }
 
   async _ensureInit() {
     await this._initPromise;
     if (this.handle === null) {
-      this.handle = wasmExports().create_compressor();
+      const hasContentSize = this.contentSize !== undefined ? 1 : 0;
+      this.handle = wasmExports().create_compressor(
+        this.level,
+        ...this.frame,
+        hasContentSize,
+        this.contentSize ?? 0,
+        dictionaryHandle(this.dictionary)
+      );
       if (this.handle === 0) {
-        throw new Error('Failed to create compressor');
+        throw exportError('Failed to create compressor');
       }
     }
   }
 
   async compressChunk(input, finish = false) {
+    return this._compressChunk(input, finish ? FLUSH_FINISH : FLUSH_NONE);
+  }
+
+  /**
+   * Compress `input` and force out everything written so far, so a receiver
+   * can decode it, without ending the stream.
+   * @param {Uint8Array} [input]
+   * @param {{ full?: boolean }} [options] full: treated like a sync flush (no resettable history)
+   * @returns {Promise<Uint8Array>}
+   */
+  async flush(input = new Uint8Array(0), options = {}) {
+    return this._compressChunk(input, options.full ? FLUSH_FULL : FLUSH_SYNC);
+  }
+
+  async _compressChunk(input, mode) {
+    const finish = mode === FLUSH_FINISH;
     await this._ensureInit();
     if (this.handle === 0) {
-      throw new Error('Compressor already destroyed');
+      throw closedError('Compressor', this.finished);
     }
     
     const view = toBytes(input);
@@ -176,27 +470,31 @@ export class StreamingCompressor {
     
     try {
       memoryU8().set(view, inPtr);
-      const written = wasmExports().compress_chunk(this.handle, inPtr, len, outPtr, outLen, finish ? 1 : 0);
+      const written = wasmExports().compress_chunk(this.handle, inPtr, len, outPtr, outLen, mode);
       
       if (written < 0) {
-        if (written === -1) {
-          throw new Error('Compression failed');
+        if (isErrorCode(written)) {
+          this.handle = 0;
+          throw exportError('Compression failed', written);
         } else {
           // Negative value indicates needed buffer size
           free(outPtr, outLen);
           const neededLen = -written;
           const newOutPtr = alloc(neededLen);
-          memoryU8().set(view, inPtr);
-          const retryWritten = wasmExports().compress_chunk(this.handle, inPtr, len, newOutPtr, neededLen, finish ? 1 : 0);
+          // The output is queued on the Rust side; collect it without
+          // feeding the input again.
+          const retryWritten = wasmExports().compress_chunk(this.handle, inPtr, 0, newOutPtr, neededLen, FLUSH_NONE);
           if (retryWritten < 0) {
             free(newOutPtr, neededLen);
-            throw new Error('Compression failed after retry');
+            this.handle = 0;
+            throw exportError('Compression failed after retry', retryWritten);
           }
           const result = memoryU8().slice(newOutPtr, newOutPtr + retryWritten);
           free(newOutPtr, neededLen);
           free(inPtr, len);
           if (finish) {
             this.handle = 0;
+            this.finished = true;
           }
           return result;
         }
@@ -215,12 +513,14 @@ export class StreamingCompressor {
       
       if (finish) {
         this.handle = 0;
+        this.finished = true;
       }
       
       return result;
     } catch (error) {
       free(outPtr, outLen);
       free(inPtr, len);
+      if (error.code) throw error;
       throw new Error(`Compression failed: ${error.message}`);
     }
   }
@@ -238,17 +538,33 @@ export class StreamingCompressor {
 // 
 // See note above about why we use manual handles instead of createTransformStream()
 export class StreamingDecompressor {
-  constructor() {
+  constructor(options = {}) {
     this._initPromise = ensureReady();
+    this.maxOutputSize = options.maxOutputSize ?? 0;
+    this.maxRatio = options.maxRatio ?? 0;
+    this.dictionary = options.dictionary;
+    // Descriptor of the current (or last) frame, once its header has arrived.
+    this.frameDescriptor = null;
     this.handle = null;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
+  }
+
+  _readDescriptor() {
+    const descriptor = collectDescriptor((outPtr, outLen) =>
+      wasmExports().lz4_decompressor_frame_descriptor(this.handle, outPtr, outLen)
+    );
+    if (descriptor !== null) this.frameDescriptor = descriptor;
   }
 
   async _ensureInit() {
     await this._initPromise;
     if (this.handle === null) {
-      this.handle = wasmExports().create_decompressor();
+      this.handle = wasmExports().create_decompressor(
+        this.maxOutputSize, this.maxRatio, dictionaryHandle(this.dictionary)
+      );
       if (this.handle === 0) {
-        throw new Error('Failed to create decompressor');
+        throw exportError('Failed to create decompressor');
       }
     }
   }
@@ -256,12 +572,14 @@ export class StreamingDecompressor {
   async decompressChunk(input, finish = false) {
     await this._ensureInit();
     if (this.handle === 0) {
-      throw new Error('Decompressor already destroyed');
+      throw closedError('Decompressor', this.finished);
     }
     
     const view = toBytes(input);
     const len = view.byteLength;
-    const outLen = len * 4; // Decompressed data is typically larger
+    // Output that doesn't fit stays pending on the Rust side; drain it with
+    // further calls until 0 is returned.
+    const outLen = Math.max(len * 4, 65536);
     
     const inPtr = alloc(len);
     const outPtr = alloc(outLen);
@@ -271,33 +589,19 @@ export class StreamingDecompressor {
       const written = wasmExports().decompress_chunk(this.handle, inPtr, len, outPtr, outLen, finish ? 1 : 0);
       
       if (written < 0) {
-        if (written === -1) {
-          throw new Error('Decompression failed');
-        } else {
-          // Negative value indicates needed buffer size
-          free(outPtr, outLen);
-          const neededLen = -written;
-          const newOutPtr = alloc(neededLen);
-          memoryU8().set(view, inPtr);
-          const retryWritten = wasmExports().decompress_chunk(this.handle, inPtr, len, newOutPtr, neededLen, finish ? 1 : 0);
-          if (retryWritten < 0) {
-            free(newOutPtr, neededLen);
-            throw new Error('Decompression failed after retry');
-          }
-          const result = memoryU8().slice(newOutPtr, newOutPtr + retryWritten);
-          free(newOutPtr, neededLen);
-          free(inPtr, len);
-          if (finish) {
-            this.handle = 0;
-          }
-          return result;
-        }
+        this.handle = 0;
+        throw exportError('Decompression failed', written);
       }
+      this._readDescriptor();
       
       if (written === 0) {
-        // No output yet (buffering)
+        // No output yet (buffering), or the stream is fully drained
         free(outPtr, outLen);
         free(inPtr, len);
+        if (finish) {
+          this.handle = 0;
+          this.finished = true;
+        }
         return new Uint8Array(0);
       }
       
@@ -305,14 +609,11 @@ export class StreamingDecompressor {
       free(outPtr, outLen);
       free(inPtr, len);
       
-      if (finish) {
-        this.handle = 0;
-      }
-      
       return result;
     } catch (error) {
       free(outPtr, outLen);
       free(inPtr, len);
+      if (error.code) throw error;
       throw new Error(`Decompression failed: ${error.message}`);
     }
   }
@@ -326,11 +627,77 @@ export class StreamingDecompressor {
   }
 }
 
+/**
+ * Read the descriptor of an LZ4 frame without decompressing it.
+ * @param {Uint8Array} input LZ4 frame data, at least up to the end of the header
+ * @returns {Promise<FrameOptions & { contentSize?: number, dictId?: number }>}
+ */
+export async function readFrameDescriptor(input) {
+  await ensureReady();
+
+  const view = toBytes(input);
+  const len = view.byteLength;
+  const inPtr = alloc(len);
+  try {
+    memoryU8().set(view, inPtr);
+    const descriptor = collectDescriptor((outPtr, outLen) =>
+      wasmExports().read_lz4_frame_descriptor(inPtr, len, outPtr, outLen)
+    );
+    if (descriptor === null) {
+      throw exportError('Invalid LZ4 frame header');
+    }
+    return descriptor;
+  } finally {
+    free(inPtr, len);
+  }
+}
+
 // One-shot decompression
-export async function decompress(input) {
+export async function decompress(input, options = {}) {
+  await ensureReady();
+  
+  const view = toBytes(input);
+  const len = view.byteLength;
+  const maxOutputSize = options.maxOutputSize ?? 0;
+  const maxRatio = options.maxRatio ?? 0;
+  const dict = dictionaryHandle(options.dictionary);
+  
+  // Start with a reasonable estimate (10x compressed size)
+  let outLen = Math.max(len * 10, 65536);
+  
+  const inPtr = alloc(len);
+  let outPtr = alloc(outLen);
+  
   try {
-    return decompress_lz4(input);
+    memoryU8().set(view, inPtr);
+    let written = wasmExports().decompress_lz4(inPtr, len, outPtr, outLen, maxOutputSize, maxRatio, dict);
+    if (isErrorCode(written)) {
+      throw exportError('Decompression failed', written);
+    }
+    
+    // Any other negative value means we need more space
+    if (written < 0) {
+      const neededLen = -written;
+      free(outPtr, outLen);
+      outLen = neededLen;
+      outPtr = alloc(outLen);
+      
+      // Retry with correct size
+      written = wasmExports().decompress_lz4(inPtr, len, outPtr, outLen, maxOutputSize, maxRatio, dict);
+      
+      if (written < 0) {
+        throw exportError('Decompression failed', written);
+      }
+    }
+    
+    const result = memoryU8().slice(outPtr, outPtr + written);
+    free(inPtr, len);
+    free(outPtr, outLen);
+    return result;
   } catch (error) {
+    free(inPtr, len);
+    free(outPtr, outLen);
+    if (error.code) throw error;
     throw new Error(`Decompression failed: ${error.message}`);
   }
 }
@@ -351,11 +718,12 @@ function requireTransformStream() {
  * This uses the LZ4 *frame* streaming API so the output can be decoded without
  * knowing the original size ahead of time (good for fetch request bodies).
  *
+ * @param {{ level?: number }} [options] see StreamingCompressor
  * @returns {TransformStream<Uint8Array, Uint8Array>}
  */
-export function createCompressionStream() {
+export function createCompressionStream(options = {}) {
   requireTransformStream();
-  const enc = new StreamingCompressor();
+  const enc = new StreamingCompressor(options);
 
   return new TransformStream({
     async transform(chunk, controller) {
@@ -373,23 +741,36 @@ export function createCompressionStream() {
 /**
  * Create a TransformStream that LZ4-decompresses a byte stream.
  *
- * Note: current LZ4 frame streaming decompression buffers until finish=true,
- * so output is typically produced during `flush()`.
+ * Each LZ4 block is decoded as soon as its bytes arrive, so output flows as
+ * the input does and memory stays bounded by the frame's block size.
  *
+ * @param {{ maxOutputSize?: number, maxRatio?: number, dictionary?: Lz4Dictionary }} [options]
  * @returns {TransformStream<Uint8Array, Uint8Array>}
  */
-export function createDecompressionStream() {
+export function createDecompressionStream(options = {}) {
   requireTransformStream();
-  const dec = new StreamingDecompressor();
+  const dec = new StreamingDecompressor(options);
+
+  async function drain(controller, finish) {
+    // Drain any remaining output buffered on the Rust side.
+    while (true) {
+      const out = await dec.decompressChunk(new Uint8Array(0), finish);
+      if (!out.length) break;
+      controller.enqueue(out);
+      // Only pass finish once; subsequent drains should be finish=false.
+      finish = false;
+    }
+  }
 
   return new TransformStream({
     async transform(chunk, controller) {
       const out = await dec.decompressChunk(toBytes(chunk), false);
       if (out.length) controller.enqueue(out);
+      await drain(controller, false);
     },
     async flush(controller) {
-      const out = await dec.decompressChunk(new Uint8Array(0), true);
-      if (out.length) controller.enqueue(out);
+      // Finish and drain the remainder.
+      await drain(controller, true);
     },
   });
 }
@@ -397,17 +778,19 @@ export function createDecompressionStream() {
 /**
  * Convenience helper: readable.pipeThrough(createCompressionStream()).
  * @param {ReadableStream<Uint8Array>} readable
+ * @param {{ level?: number }} [options]
  */
-export function compressStream(readable) {
-  return readable.pipeThrough(createCompressionStream());
+export function compressStream(readable, options = {}) {
+  return readable.pipeThrough(createCompressionStream(options));
 }
 
 /**
  * Convenience helper: readable.pipeThrough(createDecompressionStream()).
  * @param {ReadableStream<Uint8Array>} readable
+ * @param {{ maxOutputSize?: number, maxRatio?: number, dictionary?: Lz4Dictionary }} [options]
  */
-export function decompressStream(readable) {
-  return readable.pipeThrough(createDecompressionStream());
+export function decompressStream(readable, options = {}) {
+  return readable.pipeThrough(createDecompressionStream(options));
 }
 
 export { wasmExports };
@@ -0,0 +1,408 @@
+use codec_zstd::{ZstdCompressor, ZstdDecompressor, ZstdError};
+use core_api::abi::{self, ErrorCode};
+use core_api::handles::{HandleKind, HandleRegistry};
+use core_api::{
+    CompressionOptions, Compressor, DecompressionOptions, Decompressor, ErrorKind, Flush,
+    HasErrorKind,
+};
+use std::sync::Mutex;
+
+// --- wasm-bindgen-lite bindings ---
+
+/// Allocate `len` bytes for JS to fill or read.
+///
+/// # Safety
+///
+/// `len` must be non-zero.
+#[no_mangle]
+pub unsafe extern "C" fn alloc_bytes(len: usize) -> *mut u8 {
+    use std::alloc::{alloc, Layout};
+    let layout = Layout::from_size_align(len, std::mem::align_of::<u8>()).unwrap();
+    alloc(layout)
+}
+
+/// Free a buffer from `alloc_bytes`.
+///
+/// # Safety
+///
+/// `ptr` must come from `alloc_bytes(len)` with the same `len`, and must not
+/// have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn free_bytes(ptr: *mut u8, len: usize) {
+    use std::alloc::{dealloc, Layout};
+    let layout = Layout::from_size_align(len, std::mem::align_of::<u8>()).unwrap();
+    dealloc(ptr, layout);
+}
+
+/// Code of the last failed call (see `core_api::abi::ErrorCode`).
+#[no_mangle]
+pub extern "C" fn last_error_code() -> i32 {
+    abi::last_error_code() as i32
+}
+
+/// Copy the UTF-8 description of the last failed call into `out_ptr`.
+/// Returns its length, or the negative size needed.
+///
+/// # Safety
+///
+/// `out_ptr` must be valid for writes of `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn last_error_message(out_ptr: *mut u8, out_len: usize) -> isize {
+    let message = abi::last_error_message();
+    if message.len() > out_len {
+        return abi::needed_size(message.len());
+    }
+    std::ptr::copy_nonoverlapping(message.as_ptr(), out_ptr, message.len());
+    message.len() as isize
+}
+
+// Static storage for streaming compressors and decompressors
+core_api::handle_entries! {
+    enum Entry {
+        Compressor(ZstdCompressorState),
+        Decompressor(ZstdDecompressorState),
+    }
+}
+
+static HANDLES: Mutex<HandleRegistry<Entry>> = Mutex::new(HandleRegistry::new());
+
+/// Store a new object and return its handle, or 0 when every handle is taken.
+fn insert_handle<K: HandleKind<Entry>>(value: K) -> u32 {
+    HANDLES.lock().unwrap().insert(value).unwrap_or_else(|| {
+        abi::fail(ErrorCode::Failed, "Too many open handles");
+        0
+    })
+}
+
+/// Record a codec failure for `last_error_message` and return its ABI code;
+/// `fallback` is the code for io errors the codec can't classify further.
+fn codec_error(e: ZstdError, fallback: ErrorCode) -> isize {
+    let code = match e.kind() {
+        ErrorKind::Io => fallback,
+        kind => ErrorCode::from(kind),
+    };
+    abi::fail(code, e)
+}
+
+/// Queue `out` behind the output JS hasn't collected yet, failing rather
+/// than aborting when memory runs out.
+fn queue_output(pending: &mut Vec<u8>, out: &[u8]) -> Result<(), isize> {
+    if pending.try_reserve(out.len()).is_err() {
+        return Err(abi::fail(ErrorCode::OutOfMemory, "Out of memory for pending output"));
+    }
+    pending.extend_from_slice(out);
+    Ok(())
+}
+
+/// Build decompression limits from the ABI arguments; 0 means unlimited.
+fn decompression_options(max_output_size: usize, max_ratio: u32) -> DecompressionOptions {
+    DecompressionOptions {
+        max_output_size: (max_output_size != 0).then_some(max_output_size as u64),
+        max_ratio: (max_ratio != 0).then_some(max_ratio),
+    }
+}
+
+// ============================================================================
+// One-shot Compression API
+// ============================================================================
+
+/// Compress `in_ptr` into `out_ptr` as one zstd stream at the default level.
+/// Returns the compressed length, the negative size needed, or an error code.
+///
+/// # Safety
+///
+/// `in_ptr` must be valid for reads of `in_len` bytes and `out_ptr` for writes
+/// of `out_len` bytes, and the two ranges must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn compress_zstd(
+    in_ptr: *const u8,
+    in_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> isize {
+    let input = std::slice::from_raw_parts(in_ptr, in_len);
+    let opts = CompressionOptions::default();
+
+    match <ZstdCompressor as Compressor>::compress_all(input, opts) {
+        Ok(out) => {
+            if out.len() > out_len {
+                return abi::needed_size(out.len());
+            }
+            std::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len());
+            out.len() as isize
+        }
+        Err(e) => codec_error(e, ErrorCode::Failed),
+    }
+}
+
+// ============================================================================
+// Streaming Compression API
+// ============================================================================
+
+struct ZstdCompressorState {
+    compressor: ZstdCompressor,
+    // Compressed bytes that didn't fit the caller's buffer, delivered on the next call
+    pending: Vec<u8>,
+    done: bool,
+}
+
+#[no_mangle]
+pub extern "C" fn create_zstd_compressor(level: u32) -> u32 {
+    let opts = CompressionOptions {
+        level: Some(level),
+        ..Default::default()
+    };
+    match ZstdCompressor::new(opts) {
+        Ok(compressor) => {
+            let state = ZstdCompressorState {
+                compressor,
+                pending: Vec::new(),
+                done: false,
+            };
+            insert_handle(state)
+        }
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
+        }
+    }
+}
+
+/// Map the `flush` byte of the chunk ABI onto a flush mode:
+/// 0 = none, 1 = finish, 2 = sync, 3 = full.
+fn flush_mode(flush: u8) -> Flush {
+    match flush {
+        0 => Flush::None,
+        2 => Flush::Sync,
+        3 => Flush::Full,
+        _ => Flush::Finish,
+    }
+}
+
+/// Feed `in_ptr` to a streaming compressor and collect its output. Returns
+/// the number of bytes written, the negative size needed (the output stays
+/// queued for a retry), or an error code.
+///
+/// # Safety
+///
+/// `in_ptr` must be valid for reads of `in_len` bytes (it is not read when
+/// `in_len` is 0) and `out_ptr` for writes of `out_len` bytes, and the two
+/// ranges must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn compress_zstd_chunk(
+    handle: u32,
+    in_ptr: *const u8,
+    in_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+    flush: u8,
+) -> isize {
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<ZstdCompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
+    };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<ZstdCompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
+
+    // Feed new input (and the flush request) to the compressor, queuing its
+    // output behind anything an earlier call couldn't deliver. A call with no
+    // input and no flush only drains.
+    let flush = flush_mode(flush);
+    if !state.done && (in_len > 0 || flush != Flush::None) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.compressor.compress_chunk(input, flush) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<ZstdCompressorState>(handle);
+                    return code;
+                }
+            }
+            Err(e) => {
+                let _ = handles.remove::<ZstdCompressorState>(handle);
+                return codec_error(e, ErrorCode::Failed);
+            }
+        }
+        if flush == Flush::Finish {
+            state.done = true;
+        }
+    }
+
+    // Deliver all pending output, or report the size needed and keep it
+    // queued for a retry with a bigger buffer.
+    let len = state.pending.len();
+    if len > out_len {
+        return abi::needed_size(len);
+    }
+    std::ptr::copy_nonoverlapping(state.pending.as_ptr(), out_ptr, len);
+    state.pending.clear();
+    if state.done {
+        let _ = handles.remove::<ZstdCompressorState>(handle);
+    }
+    len as isize
+}
+
+#[no_mangle]
+pub extern "C" fn destroy_zstd_compressor(handle: u32) {
+    let _ = HANDLES.lock().unwrap().remove::<ZstdCompressorState>(handle);
+}
+
+// ============================================================================
+// Decompression API
+// ============================================================================
+
+/// Decompress the complete zstd stream in `in_ptr` into `out_ptr`. Returns the
+/// decompressed length, the negative size needed, or an error code.
+///
+/// # Safety
+///
+/// `in_ptr` must be valid for reads of `in_len` bytes and `out_ptr` for writes
+/// of `out_len` bytes, and the two ranges must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn decompress_zstd(
+    in_ptr: *const u8,
+    in_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+    max_output_size: usize,
+    max_ratio: u32,
+) -> isize {
+    let input = std::slice::from_raw_parts(in_ptr, in_len);
+    let opts = decompression_options(max_output_size, max_ratio);
+
+    match ZstdDecompressor::decompress_all(input, opts) {
+        Ok(output) => {
+            if output.len() > out_len {
+                return abi::needed_size(output.len());
+            }
+            std::ptr::copy_nonoverlapping(output.as_ptr(), out_ptr, output.len());
+            output.len() as isize
+        }
+        Err(e) => codec_error(e, ErrorCode::CorruptData),
+    }
+}
+
+// ============================================================================
+// Streaming Decompression API
+// ============================================================================
+
+struct ZstdDecompressorState {
+    decoder: ZstdDecompressor,
+    // Pending decompressed bytes not yet returned to JS
+    pending: Vec<u8>,
+    pending_offset: usize,
+    done: bool,
+}
+
+impl ZstdDecompressorState {
+    fn new(options: DecompressionOptions) -> Result<Self, ZstdError> {
+        let decoder = ZstdDecompressor::new(options)?;
+        Ok(Self {
+            decoder,
+            pending: Vec::new(),
+            pending_offset: 0,
+            done: false,
+        })
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn create_zstd_decompressor(max_output_size: usize, max_ratio: u32) -> u32 {
+    let opts = decompression_options(max_output_size, max_ratio);
+    match ZstdDecompressorState::new(opts) {
+        Ok(state) => insert_handle(state),
+        Err(e) => {
+            codec_error(e, ErrorCode::Failed);
+            0
+        }
+    }
+}
+
+/// Feed `in_ptr` to a streaming decompressor and copy out as much output as
+/// fits. Returns the number of bytes written or an error code.
+///
+/// # Safety
+///
+/// `in_ptr` must be valid for reads of `in_len` bytes (it is not read when
+/// `in_len` is 0) and `out_ptr` for writes of `out_len` bytes, and the two
+/// ranges must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn decompress_zstd_chunk(
+    handle: u32,
+    in_ptr: *const u8,
+    in_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+    finish: u8,
+) -> isize {
+    let mut handles = HANDLES.lock().unwrap();
+    let state = match handles.get_mut::<ZstdDecompressorState>(handle) {
+        Ok(s) => s,
+        Err(e) => return abi::fail(ErrorCode::InvalidHandle, e),
+    };
+    // Every failure from here on releases the handle, whichever the direction,
+    // so JS can drop the stream on any error.
+    if state.done && in_len > 0 {
+        let _ = handles.remove::<ZstdDecompressorState>(handle);
+        return abi::fail(ErrorCode::AlreadyFinished, "Input after the stream was finished");
+    }
+
+    // Feed new compressed input (and the end-of-stream signal) to the decoder,
+    // stashing whatever it produces behind any output JS hasn't collected yet.
+    if !state.done && (in_len > 0 || finish != 0) {
+        let input = if in_len > 0 {
+            std::slice::from_raw_parts(in_ptr, in_len)
+        } else {
+            &[]
+        };
+        match state.decoder.decompress_chunk(input, finish != 0) {
+            Ok(out) => {
+                if let Err(code) = queue_output(&mut state.pending, &out) {
+                    let _ = handles.remove::<ZstdDecompressorState>(handle);
+                    return code;
+                }
+            }
+            Err(e) => {
+                let _ = handles.remove::<ZstdDecompressorState>(handle);
+                return codec_error(e, ErrorCode::CorruptData);
+            }
+        }
+        if finish != 0 {
+            state.done = true;
+        }
+    }
+
+    // Drain pending output into the caller's buffer.
+    let remaining = state.pending.len() - state.pending_offset;
+    let to_copy = remaining.min(out_len);
+    if to_copy > 0 {
+        std::ptr::copy_nonoverlapping(
+            state.pending.as_ptr().add(state.pending_offset),
+            out_ptr,
+            to_copy,
+        );
+        state.pending_offset += to_copy;
+    }
+    // If fully drained, reset buffer to avoid unbounded growth.
+    if state.pending_offset >= state.pending.len() {
+        state.pending.clear();
+        state.pending_offset = 0;
+        if state.done && to_copy == 0 {
+            let _ = handles.remove::<ZstdDecompressorState>(handle);
+        }
+    }
+    to_copy as isize
+}
+
+#[no_mangle]
+pub extern "C" fn destroy_zstd_decompressor(handle: u32) {
+    let _ = HANDLES.lock().unwrap().remove::<ZstdDecompressorState>(handle);
+}
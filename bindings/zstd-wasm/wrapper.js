@@ -0,0 +1,445 @@
+import { 
+  compress_zstd,
+  decompress_zstd,
+  wasmExports,
+  alloc,
+  free,
+  memoryU8,
+  ensureReady
+} from './core.js';
+
+// Flush modes understood by compress_zstd_chunk
+const FLUSH_NONE = 0;
+const FLUSH_FINISH = 1;
+const FLUSH_SYNC = 2;
+const FLUSH_FULL = 3;
+
+function toBytes(input) {
+  if (input instanceof Uint8Array) return input;
+  if (ArrayBuffer.isView(input)) return new Uint8Array(input.buffer, input.byteOffset, input.byteLength);
+  if (input instanceof ArrayBuffer) return new Uint8Array(input);
+  throw new TypeError("Expected a TypedArray or ArrayBuffer");
+}
+
+// Error codes returned by the exports (`core_api::abi::ErrorCode`); any
+// other negative value is the output size needed.
+const ERROR_CODES = {
+  [-1]: 'FAILED',
+  [-2]: 'LIMIT_EXCEEDED',
+  [-3]: 'INVALID_HANDLE',
+  [-4]: 'CORRUPT_DATA',
+  [-5]: 'CHECKSUM_MISMATCH',
+  [-6]: 'TRUNCATED_INPUT',
+  [-7]: 'ALREADY_FINISHED',
+  [-8]: 'OUT_OF_MEMORY',
+};
+const LOWEST_ERROR_CODE = -8;
+
+function isErrorCode(value) {
+  return value < 0 && value >= LOWEST_ERROR_CODE;
+}
+
+/**
+ * Error thrown when a call into the wasm module fails. `code` is one of the
+ * names in ERROR_CODES, e.g. 'CORRUPT_DATA' or 'LIMIT_EXCEEDED'.
+ */
+export class CodecError extends Error {
+  constructor(message, code) {
+    super(message);
+    this.name = 'CodecError';
+    this.code = code;
+  }
+}
+
+// The module's description of its last failure.
+function lastErrorMessage() {
+  let outLen = 256;
+  let outPtr = alloc(outLen);
+  try {
+    let written = wasmExports().last_error_message(outPtr, outLen);
+    if (written < 0) {
+      free(outPtr, outLen);
+      outLen = -written;
+      outPtr = alloc(outLen);
+      written = wasmExports().last_error_message(outPtr, outLen);
+    }
+    return new TextDecoder().decode(memoryU8().subarray(outPtr, outPtr + Math.max(written, 0)));
+  } finally {
+    free(outPtr, outLen);
+  }
+}
+
+// Error for an export that returned `code`; create exports, which return 0,
+// leave it to the module's last error code.
+function exportError(context, code = wasmExports().last_error_code()) {
+  return new CodecError(`${context}: ${lastErrorMessage()}`, ERROR_CODES[code] ?? 'FAILED');
+}
+
+// Error for a call on a stream that was finished or destroyed in JS already.
+// A chunk export that fails releases the stream's handle, for compressors and
+// decompressors alike, so a stream is closed after any error and later calls
+// get this error too.
+function closedError(kind, finished) {
+  return finished
+    ? new CodecError(`${kind} already finished`, 'ALREADY_FINISHED')
+    : new CodecError(`${kind} already destroyed`, 'INVALID_HANDLE');
+}
+
+// ============================================================================
+// One-shot Compression API
+// ============================================================================
+
+export async function compress(input, options = {}) {
+  try {
+    if (options.level === undefined) return compress_zstd(input);
+    // Non-default levels go through a single-use streaming handle.
+    const enc = new StreamingCompressor(options);
+    return await enc.compressChunk(input, true);
+  } catch (error) {
+    if (error.code) throw error;
+    throw new Error(`Compression failed: ${error.message}`);
+  }
+}
+
+// ============================================================================
+// One-shot Decompression API
+// ============================================================================
+
+export async function decompress(input, options = {}) {
+  await ensureReady();
+  
+  const view = toBytes(input);
+  const len = view.byteLength;
+  const maxOutputSize = options.maxOutputSize ?? 0;
+  const maxRatio = options.maxRatio ?? 0;
+  
+  // Start with a reasonable estimate (10x compressed size)
+  let outLen = Math.max(len * 10, 65536);
+  
+  const inPtr = alloc(len);
+  let outPtr = alloc(outLen);
+  
+  try {
+    memoryU8().set(view, inPtr);
+    let written = wasmExports().decompress_zstd(inPtr, len, outPtr, outLen, maxOutputSize, maxRatio);
+    if (isErrorCode(written)) {
+      throw exportError('Decompression failed', written);
+    }
+    
+    // Any other negative value means we need more space
+    if (written < 0) {
+      const neededLen = -written;
+      free(outPtr, outLen);
+      outLen = neededLen;
+      outPtr = alloc(outLen);
+      
+      // Retry with correct size
+      written = wasmExports().decompress_zstd(inPtr, len, outPtr, outLen, maxOutputSize, maxRatio);
+      
+      if (written < 0) {
+        throw exportError('Decompression failed', written);
+      }
+    }
+    
+    const result = memoryU8().slice(outPtr, outPtr + written);
+    free(inPtr, len);
+    free(outPtr, outLen);
+    return result;
+  } catch (error) {
+    free(inPtr, len);
+    free(outPtr, outLen);
+    if (error.code) throw error;
+    throw new Error(`Decompression failed: ${error.message}`);
+  }
+}
+
+// ============================================================================
+// Streaming Compression API
+// ============================================================================
+
+export class StreamingCompressor {
+  constructor(options = {}) {
+    this._initPromise = ensureReady();
+    this.level = options.level ?? 3;
+    this.handle = null;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
+  }
+
+  async _ensureInit() {
+    await this._initPromise;
+    if (this.handle === null) {
+      this.handle = wasmExports().create_zstd_compressor(this.level);
+      if (this.handle === 0) {
+        throw exportError('Failed to create compressor');
+      }
+    }
+  }
+
+  async compressChunk(input, finish = false) {
+    return this._compressChunk(input, finish ? FLUSH_FINISH : FLUSH_NONE);
+  }
+
+  /**
+   * Compress `input` and force out everything written so far, so a receiver
+   * can decode it, without ending the stream.
+   * @param {Uint8Array} [input]
+   * @param {{ full?: boolean }} [options] full: same as a sync flush (each flush closes an independent frame)
+   * @returns {Promise<Uint8Array>}
+   */
+  async flush(input = new Uint8Array(0), options = {}) {
+    return this._compressChunk(input, options.full ? FLUSH_FULL : FLUSH_SYNC);
+  }
+
+  async _compressChunk(input, mode) {
+    const finish = mode === FLUSH_FINISH;
+    await this._ensureInit();
+    if (this.handle === 0) {
+      throw closedError('Compressor', this.finished);
+    }
+    
+    const view = toBytes(input);
+    const len = view.byteLength;
+    const outLen = len + 1024;
+    
+    const inPtr = alloc(len);
+    const outPtr = alloc(outLen);
+    
+    try {
+      memoryU8().set(view, inPtr);
+      const written = wasmExports().compress_zstd_chunk(this.handle, inPtr, len, outPtr, outLen, mode);
+      
+      if (written < 0) {
+        if (isErrorCode(written)) {
+          this.handle = 0;
+          throw exportError('Compression failed', written);
+        } else {
+          // Negative value indicates needed buffer size
+          free(outPtr, outLen);
+          const neededLen = -written;
+          const newOutPtr = alloc(neededLen);
+          // The output is queued on the Rust side; collect it without
+          // feeding the input again.
+          const retryWritten = wasmExports().compress_zstd_chunk(this.handle, inPtr, 0, newOutPtr, neededLen, FLUSH_NONE);
+          if (retryWritten < 0) {
+            free(newOutPtr, neededLen);
+            this.handle = 0;
+            throw exportError('Compression failed after retry', retryWritten);
+          }
+          const result = memoryU8().slice(newOutPtr, newOutPtr + retryWritten);
+          free(newOutPtr, neededLen);
+          free(inPtr, len);
+          if (finish) {
+            this.handle = 0;
+            this.finished = true;
+          }
+          return result;
+        }
+      }
+      
+      if (written === 0) {
+        free(outPtr, outLen);
+        free(inPtr, len);
+        return new Uint8Array(0);
+      }
+      
+      const result = memoryU8().slice(outPtr, outPtr + written);
+      free(outPtr, outLen);
+      free(inPtr, len);
+      
+      if (finish) {
+        this.handle = 0;
+        this.finished = true;
+      }
+      return result;
+    } catch (error) {
+      free(outPtr, outLen);
+      free(inPtr, len);
+      if (error.code) throw error;
+      throw new Error(`Compression failed: ${error.message}`);
+    }
+  }
+
+  async destroy() {
+    await this._initPromise;
+    if (this.handle !== 0 && this.handle !== null) {
+      wasmExports().destroy_zstd_compressor(this.handle);
+      this.handle = 0;
+    }
+  }
+}
+
+// ============================================================================
+// Streaming Decompression API (WASM)
+// ============================================================================
+
+export class StreamingDecompressor {
+  constructor(options = {}) {
+    this._initPromise = ensureReady();
+    this.maxOutputSize = options.maxOutputSize ?? 0;
+    this.maxRatio = options.maxRatio ?? 0;
+    this.handle = null;
+    // Set once the stream is finished and its handle released.
+    this.finished = false;
+  }
+
+  async _ensureInit() {
+    await this._initPromise;
+    if (this.handle === null) {
+      this.handle = wasmExports().create_zstd_decompressor(this.maxOutputSize, this.maxRatio);
+      if (this.handle === 0) {
+        throw exportError('Failed to create decompressor');
+      }
+    }
+  }
+
+  async decompressChunk(input, finish = false) {
+    await this._ensureInit();
+    if (this.handle === 0) {
+      throw closedError('Decompressor', this.finished);
+    }
+
+    const view = toBytes(input);
+    const len = view.byteLength;
+
+    // Heuristic: zstd can expand a lot; start with a decent minimum.
+    const outLen = Math.max(len * 8, 65536);
+
+    const inPtr = alloc(len);
+    const outPtr = alloc(outLen);
+
+    try {
+      memoryU8().set(view, inPtr);
+      const written = wasmExports().decompress_zstd_chunk(
+        this.handle,
+        inPtr,
+        len,
+        outPtr,
+        outLen,
+        finish ? 1 : 0
+      );
+
+      if (written < 0) {
+        this.handle = 0;
+        throw exportError('Decompression failed', written);
+      }
+
+      if (written === 0) {
+        free(inPtr, len);
+        free(outPtr, outLen);
+        if (finish) {
+          this.handle = 0;
+          this.finished = true;
+        }
+        return new Uint8Array(0);
+      }
+
+      // Output may still be pending on the Rust side after finish; the handle
+      // is released once a call returns 0, so keep it until then.
+      const result = memoryU8().slice(outPtr, outPtr + written);
+      free(inPtr, len);
+      free(outPtr, outLen);
+      return result;
+    } catch (error) {
+      free(inPtr, len);
+      free(outPtr, outLen);
+      if (error.code) throw error;
+      throw new Error(`Decompression failed: ${error.message}`);
+    }
+  }
+
+  async destroy() {
+    await this._initPromise;
+    if (this.handle !== 0 && this.handle !== null) {
+      wasmExports().destroy_zstd_decompressor(this.handle);
+      this.handle = 0;
+    }
+  }
+}
+
+// ============================================================================
+// Ergonomic streaming helpers (Web Streams)
+// ============================================================================
+
+function requireTransformStream() {
+  if (typeof TransformStream === 'undefined') {
+    throw new Error('TransformStream is not available in this runtime');
+  }
+}
+
+/**
+ * Create a TransformStream that zstd-compresses a byte stream.
+ *
+ * @param {{ level?: number }} [options]
+ * @returns {TransformStream<Uint8Array, Uint8Array>}
+ */
+export function createCompressionStream(options = {}) {
+  requireTransformStream();
+  const enc = new StreamingCompressor(options);
+
+  return new TransformStream({
+    async transform(chunk, controller) {
+      const out = await enc.compressChunk(toBytes(chunk), false);
+      if (out.length) controller.enqueue(out);
+    },
+    async flush(controller) {
+      const out = await enc.compressChunk(new Uint8Array(0), true);
+      if (out.length) controller.enqueue(out);
+    },
+  });
+}
+
+/**
+ * Create a TransformStream that zstd-decompresses a byte stream.
+ *
+ * @param {{ maxOutputSize?: number, maxRatio?: number }} [options]
+ * @returns {TransformStream<Uint8Array, Uint8Array>}
+ */
+export function createDecompressionStream(options = {}) {
+  requireTransformStream();
+  const dec = new StreamingDecompressor(options);
+
+  async function drain(controller, finish) {
+    // Drain any remaining output buffered on the Rust side.
+    while (true) {
+      const out = await dec.decompressChunk(new Uint8Array(0), finish);
+      if (!out.length) break;
+      controller.enqueue(out);
+      // Only pass finish once; subsequent drains should be finish=false.
+      finish = false;
+    }
+  }
+
+  return new TransformStream({
+    async transform(chunk, controller) {
+      const out = await dec.decompressChunk(toBytes(chunk), false);
+      if (out.length) controller.enqueue(out);
+      await drain(controller, false);
+    },
+    async flush(controller) {
+      // Finish and drain the remainder.
+      await drain(controller, true);
+    },
+  });
+}
+
+/**
+ * Convenience helper: readable.pipeThrough(createCompressionStream()).
+ * @param {ReadableStream<Uint8Array>} readable
+ * @param {{ level?: number }} [options]
+ */
+export function compressStream(readable, options = {}) {
+  return readable.pipeThrough(createCompressionStream(options));
+}
+
+/**
+ * Convenience helper: readable.pipeThrough(createDecompressionStream()).
+ * @param {ReadableStream<Uint8Array>} readable
+ * @param {{ maxOutputSize?: number, maxRatio?: number }} [options]
+ */
+export function decompressStream(readable, options = {}) {
+  return readable.pipeThrough(createDecompressionStream(options));
+}
+
+export { wasmExports };
+
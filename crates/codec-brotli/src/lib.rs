@@ -1,97 +1,444 @@
-use core_api::{CompressionOptions, Compressor, Flush};
+use core_api::{
+    CodecError, CompressionOptions, Compressor, DecompressionLimits, DecompressionOptions,
+    Decompressor, ErrorKind, Flush, HasErrorKind, LimitExceeded,
+};
+use brotli::enc::backward_references::BrotliEncoderMode;
+use brotli::enc::encode::{BrotliEncoderOperation, BrotliEncoderStateStruct};
+use brotli::enc::interface::{PredictionModeContextMap, StaticCommand};
+use brotli::enc::{InputPair, InputReferenceMut, StandardAlloc};
+use sha2::{Digest, Sha256};
 use std::io::Write;
 
 /// Error type for brotli compression.
 #[derive(Debug)]
 pub enum BrotliError {
     Io(std::io::Error),
-    Other(String),
+    /// `window_log` outside 10..=30 (values above 24 select large-window mode).
+    InvalidWindowLog(u32),
+    /// Decompressed output ran past a `DecompressionOptions` limit.
+    LimitExceeded(LimitExceeded),
+    /// Any other failure, with its kind.
+    Codec(CodecError),
 }
 
 impl std::fmt::Display for BrotliError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             BrotliError::Io(e) => write!(f, "IO error: {}", e),
-            BrotliError::Other(msg) => write!(f, "{}", msg),
+            BrotliError::InvalidWindowLog(w) => {
+                write!(f, "Invalid brotli window log {} (expected 10..=30)", w)
+            }
+            BrotliError::LimitExceeded(e) => write!(f, "{}", e),
+            BrotliError::Codec(e) => write!(f, "{}", e),
         }
     }
 }
 
 impl std::error::Error for BrotliError {}
 
+impl BrotliError {
+    /// A `Codec` error of `kind`.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        BrotliError::Codec(CodecError::new(kind, message))
+    }
+}
+
+impl HasErrorKind for BrotliError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            BrotliError::Io(e) => ErrorKind::from_io(e),
+            BrotliError::InvalidWindowLog(_) => ErrorKind::InvalidOption,
+            BrotliError::LimitExceeded(_) => ErrorKind::LimitExceeded,
+            BrotliError::Codec(e) => e.kind(),
+        }
+    }
+}
+
 impl From<std::io::Error> for BrotliError {
     fn from(e: std::io::Error) -> Self {
-        BrotliError::Io(e)
+        // The decoder reports a tripped limit through its output sink's io::Error.
+        match e.get_ref().and_then(|inner| inner.downcast_ref::<LimitExceeded>()) {
+            Some(limit) => BrotliError::LimitExceeded(*limit),
+            None => BrotliError::Io(e),
+        }
+    }
+}
+
+// Smallest and largest `lgwin` brotli accepts; anything above
+// `BROTLI_MAX_WINDOW_LOG` needs the (non-RFC 7932) large-window mode.
+const BROTLI_MIN_WINDOW_LOG: u32 = 10;
+const BROTLI_MAX_WINDOW_LOG: u32 = 24;
+const BROTLI_LARGE_MAX_WINDOW_LOG: u32 = 30;
+
+/// Build encoder params from the common options.
+fn encoder_params(
+    options: &CompressionOptions,
+) -> Result<brotli::enc::BrotliEncoderParams, BrotliError> {
+    let level = options.level.unwrap_or(6);
+    let mut params = brotli::enc::BrotliEncoderParams {
+        quality: level as i32,
+        ..Default::default()
+    };
+    if let Some(window_log) = options.window_log {
+        if !(BROTLI_MIN_WINDOW_LOG..=BROTLI_LARGE_MAX_WINDOW_LOG).contains(&window_log) {
+            return Err(BrotliError::InvalidWindowLog(window_log));
+        }
+        params.lgwin = window_log as i32;
+        params.large_window = window_log > BROTLI_MAX_WINDOW_LOG;
+    }
+    Ok(params)
+}
+
+/// Container around the brotli stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BrotliFormat {
+    /// A plain brotli stream (`Content-Encoding: br`).
+    #[default]
+    Brotli,
+    /// Dictionary-compressed brotli (`Content-Encoding: dcb`, RFC 9842): a
+    /// 36-byte header naming the dictionary by its SHA-256, then a brotli
+    /// stream compressed against that dictionary. Needs a dictionary.
+    Dcb,
+}
+
+/// Magic number that starts a `dcb` stream.
+const DCB_MAGIC: [u8; 4] = [0xff, 0x44, 0x43, 0x42];
+
+/// SHA-256 of a dictionary, as carried in the `dcb` header and advertised
+/// in the `Available-Dictionary` request header.
+pub fn dictionary_hash(dictionary: &[u8]) -> [u8; 32] {
+    Sha256::digest(dictionary).into()
+}
+
+/// The header that starts a `dcb` stream compressed against `dictionary`.
+fn dcb_header(dictionary: &[u8]) -> Vec<u8> {
+    let mut header = DCB_MAGIC.to_vec();
+    header.extend_from_slice(&dictionary_hash(dictionary));
+    header
+}
+
+/// Kind of input the encoder tunes its context modelling for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BrotliMode {
+    #[default]
+    Generic,
+    /// UTF-8 text.
+    Text,
+    /// WOFF 2.0 font data.
+    Font,
+}
+
+impl From<BrotliMode> for BrotliEncoderMode {
+    fn from(mode: BrotliMode) -> Self {
+        match mode {
+            BrotliMode::Generic => BrotliEncoderMode::BROTLI_MODE_GENERIC,
+            BrotliMode::Text => BrotliEncoderMode::BROTLI_MODE_TEXT,
+            BrotliMode::Font => BrotliEncoderMode::BROTLI_MODE_FONT,
+        }
     }
 }
 
+/// Brotli-specific compressor settings.
+#[derive(Debug, Clone, Default)]
+pub struct BrotliOptions {
+    pub format: BrotliFormat,
+    pub mode: BrotliMode,
+    /// Expected total input size, which lets the encoder size its window
+    /// and hash tables. `compress_all` sets it to the input length.
+    pub size_hint: Option<usize>,
+    /// Custom dictionary the stream may reference as if it preceded the
+    /// input. Only the last `2^window_log - 16` bytes are reachable, so the
+    /// window should cover the dictionary. The decompressor needs the same bytes.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+fn no_metablock_callback(
+    _: &mut PredictionModeContextMap<InputReferenceMut>,
+    _: &mut [StaticCommand],
+    _: InputPair,
+    _: &mut StandardAlloc,
+) {
+}
+
 /// Brotli compressor.
+///
+/// Drives the encoder state directly rather than through
+/// `brotli::CompressorWriter`, which can't take a custom dictionary.
 pub struct BrotliCompressor {
-    encoder: brotli::CompressorWriter<Vec<u8>>,
+    encoder: BrotliEncoderStateStruct<StandardAlloc>,
+    /// `dcb` header not yet emitted.
+    header: Vec<u8>,
     finished: bool,
 }
 
-impl Compressor for BrotliCompressor {
-    type Error = BrotliError;
+impl BrotliCompressor {
+    /// Create a compressor with brotli-specific settings.
+    pub fn with_options(
+        options: CompressionOptions,
+        brotli: BrotliOptions,
+    ) -> Result<Self, BrotliError> {
+        let mut encoder = BrotliEncoderStateStruct::new(StandardAlloc::default());
+        encoder.params = encoder_params(&options)?;
+        encoder.params.mode = brotli.mode.into();
+        encoder.params.size_hint = brotli.size_hint.unwrap_or(0);
 
-    fn new(options: CompressionOptions) -> Result<Self, Self::Error> {
-        let level = options.level.unwrap_or(6);
-        let params = brotli::enc::BrotliEncoderParams {
-            quality: level as i32,
-            ..Default::default()
+        let header = match (brotli.format, &brotli.dictionary) {
+            (BrotliFormat::Dcb, None) => {
+                return Err(BrotliError::new(
+                    ErrorKind::InvalidOption,
+                    "The dcb format needs a dictionary",
+                ))
+            }
+            (BrotliFormat::Dcb, Some(dictionary)) => dcb_header(dictionary),
+            (BrotliFormat::Brotli, _) => Vec::new(),
         };
-        let encoder = brotli::CompressorWriter::with_params(
-            Vec::new(),
-            4096,
-            &params,
-        );
+        if let Some(dictionary) = &brotli.dictionary {
+            encoder.set_custom_dictionary(dictionary.len(), dictionary);
+        }
         Ok(Self {
             encoder,
+            header,
             finished: false,
         })
     }
 
+    /// Run `input` through the encoder with the given operation and collect
+    /// everything it produces.
+    fn encode(
+        &mut self,
+        input: &[u8],
+        op: BrotliEncoderOperation,
+    ) -> Result<Vec<u8>, BrotliError> {
+        let mut output = std::mem::take(&mut self.header);
+        let mut available_in = input.len();
+        let mut in_offset = 0;
+        loop {
+            if output.capacity() - output.len() < 64 {
+                output.reserve(output.capacity().max(4096));
+            }
+            let mut out_offset = output.len();
+            output.resize(output.capacity(), 0);
+            let mut available_out = output.len() - out_offset;
+            let ok = self.encoder.compress_stream(
+                op,
+                &mut available_in,
+                input,
+                &mut in_offset,
+                &mut available_out,
+                &mut output,
+                &mut out_offset,
+                &mut None,
+                &mut no_metablock_callback,
+            );
+            output.truncate(out_offset);
+            if !ok {
+                return Err(BrotliError::new(ErrorKind::InvalidOption, "Brotli encoder error"));
+            }
+
+            let done = match op {
+                BrotliEncoderOperation::BROTLI_OPERATION_FINISH => self.encoder.is_finished(),
+                _ => available_in == 0 && !self.encoder.has_more_output(),
+            };
+            if done {
+                return Ok(output);
+            }
+        }
+    }
+}
+
+impl Compressor for BrotliCompressor {
+    type Error = BrotliError;
+
+    fn new(options: CompressionOptions) -> Result<Self, Self::Error> {
+        Self::with_options(options, BrotliOptions::default())
+    }
+
     fn compress_chunk(
         &mut self,
         input: &[u8],
         flush: Flush,
     ) -> Result<Vec<u8>, Self::Error> {
         if self.finished {
-            return Err(BrotliError::Other("Cannot compress after finish".to_string()));
+            return Err(BrotliError::new(
+                ErrorKind::UsedAfterFinish,
+                "Cannot compress after finish",
+            ));
         }
 
-        self.encoder.write_all(input)?;
-
-        if matches!(flush, Flush::Finish) {
-            self.finished = true;
-            self.encoder.flush()?;
-            let buffer = std::mem::take(self.encoder.get_mut());
-            Ok(buffer)
-        } else {
-            Ok(Vec::new())
-        }
+        // BROTLI_OPERATION_FLUSH emits everything written so far. Brotli
+        // cannot reset its history mid-stream, so Full behaves like Sync.
+        // Without a flush, input still being matched stays in the encoder
+        // until it emits a meta-block.
+        let op = match flush {
+            Flush::None => BrotliEncoderOperation::BROTLI_OPERATION_PROCESS,
+            Flush::Sync | Flush::Full => BrotliEncoderOperation::BROTLI_OPERATION_FLUSH,
+            Flush::Finish => {
+                self.finished = true;
+                BrotliEncoderOperation::BROTLI_OPERATION_FINISH
+            }
+        };
+        self.encode(input, op)
     }
 
     fn compress_all(
         input: &[u8],
         options: CompressionOptions,
     ) -> Result<Vec<u8>, Self::Error> {
-        let level = options.level.unwrap_or(6);
-        let params = brotli::enc::BrotliEncoderParams {
-            quality: level as i32,
+        let brotli = BrotliOptions {
+            size_hint: Some(input.len()),
             ..Default::default()
         };
-        let mut encoder = brotli::CompressorWriter::with_params(
-            Vec::new(),
-            4096,
-            &params,
-        );
-        encoder.write_all(input)?;
-        encoder.flush()?;
-        // Use into_inner to finalize the stream and get the buffer
-        // Dropping the encoder finalizes the stream
-        let buffer = encoder.into_inner();
-        Ok(buffer)
+        let mut compressor = Self::with_options(options, brotli)?;
+        compressor.compress_chunk(input, Flush::Finish)
+    }
+}
+
+/// Output sink for the decoder that checks every write against the limits,
+/// so decoding stops within one output buffer (4 KiB) of a limit.
+struct LimitedSink {
+    output: Vec<u8>,
+    limits: DecompressionLimits,
+}
+
+impl Write for LimitedSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.limits.add_output(buf.len()).map_err(std::io::Error::other)?;
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Brotli-specific decompressor settings.
+#[derive(Debug, Clone, Default)]
+pub struct BrotliDecompressOptions {
+    pub format: BrotliFormat,
+    /// Custom dictionary the stream was compressed against. For `dcb`, its
+    /// SHA-256 must match the one in the stream header.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+/// Brotli decompressor.
+pub struct BrotliDecompressor {
+    decoder: brotli::DecompressorWriter<LimitedSink>,
+    /// For `dcb`, the stream header still to be checked.
+    dcb_header: Option<Vec<u8>>,
+    /// Partial `dcb` header received so far.
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+impl BrotliDecompressor {
+    /// Create a decompressor with brotli-specific settings.
+    pub fn with_options(
+        options: DecompressionOptions,
+        brotli: BrotliDecompressOptions,
+    ) -> Result<Self, BrotliError> {
+        let sink = LimitedSink {
+            output: Vec::new(),
+            limits: DecompressionLimits::new(options),
+        };
+        let dcb_header = match (brotli.format, &brotli.dictionary) {
+            (BrotliFormat::Dcb, None) => {
+                return Err(BrotliError::new(
+                    ErrorKind::InvalidOption,
+                    "The dcb format needs a dictionary",
+                ))
+            }
+            (BrotliFormat::Dcb, Some(dictionary)) => Some(dcb_header(dictionary)),
+            (BrotliFormat::Brotli, _) => None,
+        };
+        let decoder = match brotli.dictionary {
+            Some(dictionary) => brotli::DecompressorWriter::new_with_custom_dictionary(
+                sink,
+                4096,
+                dictionary.into(),
+            ),
+            None => brotli::DecompressorWriter::new(sink, 4096),
+        };
+        Ok(Self {
+            decoder,
+            dcb_header,
+            buffer: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Collect the `dcb` header from the front of `input` and check it once
+    /// complete, returning the input that follows it.
+    fn read_dcb_header<'a>(&mut self, input: &'a [u8]) -> Result<&'a [u8], BrotliError> {
+        let Some(expected) = &self.dcb_header else {
+            return Ok(input);
+        };
+        let n = (expected.len() - self.buffer.len()).min(input.len());
+        self.buffer.extend_from_slice(&input[..n]);
+        if self.buffer.len() == expected.len() {
+            if self.buffer[..DCB_MAGIC.len()] != DCB_MAGIC {
+                return Err(BrotliError::new(ErrorKind::CorruptInput, "Invalid dcb magic number"));
+            }
+            if self.buffer != *expected {
+                return Err(BrotliError::new(
+                    ErrorKind::InvalidOption,
+                    "dcb dictionary hash mismatch",
+                ));
+            }
+            self.dcb_header = None;
+        }
+        Ok(&input[n..])
+    }
+}
+
+impl Decompressor for BrotliDecompressor {
+    type Error = BrotliError;
+
+    fn new(options: DecompressionOptions) -> Result<Self, Self::Error> {
+        Self::with_options(options, BrotliDecompressOptions::default())
+    }
+
+    fn decompress_chunk(
+        &mut self,
+        input: &[u8],
+        finish: bool,
+    ) -> Result<Vec<u8>, Self::Error> {
+        if self.finished {
+            return Err(BrotliError::new(
+                ErrorKind::UsedAfterFinish,
+                "Cannot decompress after finish",
+            ));
+        }
+
+        self.decoder.get_mut().limits.add_input(input.len());
+        let input = self.read_dcb_header(input)?;
+        self.decoder.write_all(input)?;
+
+        if finish {
+            self.finished = true;
+            if self.dcb_header.is_some() {
+                return Err(BrotliError::new(ErrorKind::UnexpectedEof, "Truncated dcb header"));
+            }
+            // All input has been decoded already, so apart from a tripped
+            // limit, close only fails when the stream stops short.
+            if let Err(e) = self.decoder.close() {
+                return Err(match BrotliError::from(e) {
+                    BrotliError::Io(_) => {
+                        BrotliError::new(ErrorKind::UnexpectedEof, "Truncated brotli stream")
+                    }
+                    e => e,
+                });
+            }
+        }
+        Ok(std::mem::take(&mut self.decoder.get_mut().output))
+    }
+
+    fn decompress_all(
+        input: &[u8],
+        options: DecompressionOptions,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let mut decoder = Self::new(options)?;
+        decoder.decompress_chunk(input, true)
     }
 }
 
@@ -114,5 +461,226 @@ mod tests {
         let result = compressor.compress_chunk(b"world", Flush::Finish).unwrap();
         assert!(!result.is_empty());
     }
-}
 
+    #[test]
+    fn test_streaming_emits_output_before_finish() {
+        let options = CompressionOptions {
+            level: Some(1),
+            ..Default::default()
+        };
+        let mut compressor = BrotliCompressor::new(options).unwrap();
+        let mut state = 1u32;
+        let chunk: Vec<u8> = (0..1 << 20)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 24) as u8
+            })
+            .collect();
+        let mut compressed = Vec::new();
+        for _ in 0..4 {
+            compressed.extend(compressor.compress_chunk(&chunk, Flush::None).unwrap());
+        }
+        assert!(!compressed.is_empty());
+        compressed.extend(compressor.compress_chunk(&[], Flush::Finish).unwrap());
+        let output = BrotliDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), chunk.repeat(4));
+    }
+
+    #[test]
+    fn test_sync_flush() {
+        let mut compressor = BrotliCompressor::new(CompressionOptions::default()).unwrap();
+        let mut decompressor = BrotliDecompressor::new(DecompressionOptions::default()).unwrap();
+
+        let out = compressor.compress_chunk(b"hello ", Flush::Sync).unwrap();
+        assert_eq!(decompressor.decompress_chunk(&out, false).unwrap(), b"hello ");
+
+        let out = compressor.compress_chunk(b"world", Flush::Full).unwrap();
+        assert_eq!(decompressor.decompress_chunk(&out, false).unwrap(), b"world");
+
+        let out = compressor.compress_chunk(b"", Flush::Finish).unwrap();
+        assert!(decompressor.decompress_chunk(&out, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_window_log() {
+        let input = b"window window window window".repeat(64);
+        for window_log in [10, 16, 24, 26] {
+            let options = CompressionOptions {
+                window_log: Some(window_log),
+                ..Default::default()
+            };
+            let compressed = BrotliCompressor::compress_all(&input, options).unwrap();
+            let output =
+                BrotliDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+            assert_eq!(output.unwrap(), input);
+        }
+
+        for window_log in [9, 31] {
+            let options = CompressionOptions {
+                window_log: Some(window_log),
+                ..Default::default()
+            };
+            assert!(matches!(
+                BrotliCompressor::new(options),
+                Err(BrotliError::InvalidWindowLog(w)) if w == window_log
+            ));
+        }
+    }
+
+    #[test]
+    fn test_mode_and_size_hint() {
+        let input = "Ünïcödé text for the text mode, repeated a few times. ".repeat(50);
+        for mode in [BrotliMode::Generic, BrotliMode::Text, BrotliMode::Font] {
+            for size_hint in [None, Some(input.len()), Some(1 << 30)] {
+                let brotli = BrotliOptions {
+                    mode,
+                    size_hint,
+                    ..Default::default()
+                };
+                let mut compressor =
+                    BrotliCompressor::with_options(CompressionOptions::default(), brotli).unwrap();
+                let compressed = compressor.compress_chunk(input.as_bytes(), Flush::Finish);
+                let output = BrotliDecompressor::decompress_all(
+                    &compressed.unwrap(),
+                    DecompressionOptions::default(),
+                );
+                assert_eq!(output.unwrap(), input.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let previous = b"function render(app) { return app.mount('#root', { hydrate: true }); }"
+            .repeat(20);
+        let mut input = previous.clone();
+        input.extend_from_slice(b"// v2");
+
+        let compress = |dictionary: Option<Vec<u8>>| {
+            let brotli = BrotliOptions {
+                dictionary,
+                ..Default::default()
+            };
+            let mut compressor =
+                BrotliCompressor::with_options(CompressionOptions::default(), brotli).unwrap();
+            compressor.compress_chunk(&input, Flush::Finish).unwrap()
+        };
+        let plain = compress(None);
+        let compressed = compress(Some(previous.clone()));
+        assert!(compressed.len() < plain.len());
+
+        let brotli = BrotliDecompressOptions {
+            dictionary: Some(previous),
+            ..Default::default()
+        };
+        let mut decoder =
+            BrotliDecompressor::with_options(DecompressionOptions::default(), brotli).unwrap();
+        assert_eq!(decoder.decompress_chunk(&compressed, true).unwrap(), input);
+
+        // Without the dictionary the back-references point before the start.
+        let output = BrotliDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert!(output.is_err());
+    }
+
+    #[test]
+    fn test_dcb() {
+        let dictionary = b"shared dictionary shared dictionary".to_vec();
+        let input = b"payload with a shared dictionary";
+        let brotli = BrotliOptions {
+            format: BrotliFormat::Dcb,
+            dictionary: Some(dictionary.clone()),
+            ..Default::default()
+        };
+        let mut compressor =
+            BrotliCompressor::with_options(CompressionOptions::default(), brotli).unwrap();
+        let compressed = compressor.compress_chunk(input, Flush::Finish).unwrap();
+        assert_eq!(compressed[..4], DCB_MAGIC);
+        assert_eq!(compressed[4..36], dictionary_hash(&dictionary));
+
+        let decoder = |dictionary| {
+            let brotli = BrotliDecompressOptions {
+                format: BrotliFormat::Dcb,
+                dictionary: Some(dictionary),
+            };
+            BrotliDecompressor::with_options(DecompressionOptions::default(), brotli).unwrap()
+        };
+        // Split inside the header.
+        let mut dec = decoder(dictionary.clone());
+        let mut output = dec.decompress_chunk(&compressed[..10], false).unwrap();
+        output.extend(dec.decompress_chunk(&compressed[10..], true).unwrap());
+        assert_eq!(output, input);
+
+        let mut dec = decoder(b"another dictionary".to_vec());
+        assert!(dec.decompress_chunk(&compressed, true).is_err());
+        let mut dec = decoder(dictionary);
+        assert!(dec.decompress_chunk(&compressed[..20], true).is_err());
+
+        let brotli = BrotliOptions {
+            format: BrotliFormat::Dcb,
+            ..Default::default()
+        };
+        assert!(BrotliCompressor::with_options(CompressionOptions::default(), brotli).is_err());
+    }
+
+    #[test]
+    fn test_decompress_roundtrip() {
+        let input = b"hello hello hello world";
+        let compressed =
+            BrotliCompressor::compress_all(input, CompressionOptions::default()).unwrap();
+        let output = BrotliDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), input);
+
+        let mut decompressor = BrotliDecompressor::new(DecompressionOptions::default()).unwrap();
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+        let mut output = decompressor.decompress_chunk(head, false).unwrap();
+        output.extend(decompressor.decompress_chunk(tail, true).unwrap());
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_decompression_limits() {
+        let input = vec![0u8; 1 << 20];
+        let compressed =
+            BrotliCompressor::compress_all(&input, CompressionOptions::default()).unwrap();
+
+        let options = DecompressionOptions {
+            max_output_size: Some(100_000),
+            ..Default::default()
+        };
+        assert!(matches!(
+            BrotliDecompressor::decompress_all(&compressed, options),
+            Err(BrotliError::LimitExceeded(LimitExceeded::OutputSize(100_000)))
+        ));
+
+        let options = DecompressionOptions {
+            max_ratio: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            BrotliDecompressor::decompress_all(&compressed, options),
+            Err(BrotliError::LimitExceeded(LimitExceeded::Ratio(100)))
+        ));
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let compressed =
+            BrotliCompressor::compress_all(b"hello world", CompressionOptions::default()).unwrap();
+        let kind = |input: &[u8]| {
+            BrotliDecompressor::decompress_all(input, Default::default()).unwrap_err().kind()
+        };
+        assert_eq!(kind(&[0xff; 16]), ErrorKind::CorruptInput);
+        assert_eq!(kind(&compressed[..compressed.len() - 2]), ErrorKind::UnexpectedEof);
+
+        let mut compressor = BrotliCompressor::new(CompressionOptions::default()).unwrap();
+        compressor.compress_chunk(b"hello", Flush::Finish).unwrap();
+        let err = compressor.compress_chunk(b"hello", Flush::Finish).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UsedAfterFinish);
+
+        let options = CompressionOptions {
+            window_log: Some(31),
+            ..Default::default()
+        };
+        assert_eq!(BrotliCompressor::new(options).err().unwrap().kind(), ErrorKind::InvalidOption);
+    }
+}
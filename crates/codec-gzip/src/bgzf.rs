@@ -0,0 +1,213 @@
+//! BGZF (blocked gzip), the container of BAM, tabix and `.vcf.gz` files.
+//!
+//! A BGZF file is a series of gzip members of at most 64 KiB, each carrying
+//! its own compressed size in a `BC` extra subfield, followed by an empty
+//! end-of-file block. Blocks are independent, so a virtual offset (block
+//! start << 16 | offset into the block's output) addresses any byte.
+
+use crate::{GzipCompressor, GzipDecompressOptions, GzipDecompressor, GzipError, GzipHeader};
+use core_api::{DecompressionOptions, Decompressor, ErrorKind, Flush};
+use flate2::Crc;
+use zlib_rs::DeflateFlush;
+
+/// The empty block that ends every BGZF file.
+pub const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Uncompressed bytes per block, as htslib writes them; leaves room for the
+/// deflate and gzip overhead so even stored data fits in 64 KiB.
+pub const BGZF_BLOCK_SIZE: usize = 0xff00;
+
+/// Largest compressed block BSIZE can describe.
+const MAX_BLOCK_LEN: usize = 0x10000;
+/// Gzip header with the 6-byte BC extra field, plus the 8-byte trailer.
+const BLOCK_OVERHEAD: usize = 18 + 8;
+
+/// Wrap raw deflate `cdata` (the compressed form of `data`) in a BGZF block.
+fn write_block(data: &[u8], cdata: &[u8], output: &mut Vec<u8>) {
+    let bsize = (cdata.len() + BLOCK_OVERHEAD - 1) as u16;
+    let mut extra = vec![b'B', b'C', 2, 0];
+    extra.extend_from_slice(&bsize.to_le_bytes());
+    let header = GzipHeader {
+        extra: Some(extra),
+        ..Default::default()
+    };
+
+    let mut crc = Crc::new();
+    crc.update(data);
+    output.extend_from_slice(&header.write(0));
+    output.extend_from_slice(cdata);
+    output.extend_from_slice(&crc.sum().to_le_bytes());
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+}
+
+/// Encode `data` as a single stored deflate block.
+fn stored(data: &[u8]) -> Vec<u8> {
+    let len = data.len() as u16;
+    let mut out = vec![0x01];
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(&(!len).to_le_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+impl GzipCompressor {
+    /// BGZF path of `compress_chunk`: buffer input into blocks, writing each
+    /// one as it fills. Sync and full flushes close the current block early;
+    /// finish also writes the EOF marker.
+    pub(crate) fn compress_bgzf(
+        &mut self,
+        input: &[u8],
+        flush: Flush,
+    ) -> Result<Vec<u8>, GzipError> {
+        let mut output = Vec::new();
+        let mut input = input;
+        loop {
+            let take = (BGZF_BLOCK_SIZE - self.block.len()).min(input.len());
+            self.block.extend_from_slice(&input[..take]);
+            input = &input[take..];
+            if self.block.len() < BGZF_BLOCK_SIZE {
+                break;
+            }
+            self.write_bgzf_block(&mut output)?;
+        }
+
+        if flush != Flush::None && !self.block.is_empty() {
+            self.write_bgzf_block(&mut output)?;
+        }
+        if flush == Flush::Finish {
+            output.extend_from_slice(&BGZF_EOF);
+        }
+        Ok(output)
+    }
+
+    fn write_bgzf_block(&mut self, output: &mut Vec<u8>) -> Result<(), GzipError> {
+        let data = std::mem::take(&mut self.block);
+        self.compress.reset();
+        let mut cdata = self.deflate(&data, DeflateFlush::Finish)?;
+        if cdata.len() + BLOCK_OVERHEAD > MAX_BLOCK_LEN {
+            cdata = stored(&data);
+        }
+        write_block(&data, &cdata, output);
+
+        // Keep the allocation for the next block.
+        self.block = data;
+        self.block.clear();
+        Ok(())
+    }
+}
+
+/// Total length of the BGZF block starting at `buf`, read from its BC
+/// subfield. Returns `None` until the whole header has arrived.
+pub fn bgzf_block_len(buf: &[u8]) -> Result<Option<usize>, GzipError> {
+    let Some((header, _)) = GzipHeader::parse(buf)? else {
+        return Ok(None);
+    };
+    // Walk the extra field's subfields: SI1 SI2 SLEN(2) data.
+    let mut extra = header.extra.as_deref().unwrap_or_default();
+    while extra.len() >= 4 {
+        let len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        let Some(data) = extra.get(4..4 + len) else {
+            break;
+        };
+        if extra[..2] == *b"BC" && len == 2 {
+            return Ok(Some(u16::from_le_bytes([data[0], data[1]]) as usize + 1));
+        }
+        extra = &extra[4 + len..];
+    }
+    Err(GzipError::new(ErrorKind::CorruptInput, "Missing BGZF block size subfield"))
+}
+
+/// Decompress a single BGZF block.
+pub fn decompress_bgzf_block(block: &[u8]) -> Result<Vec<u8>, GzipError> {
+    let gzip = GzipDecompressOptions {
+        multi_member: false,
+        ..Default::default()
+    };
+    let mut decoder = GzipDecompressor::with_options(DecompressionOptions::default(), gzip)?;
+    decoder.decompress_chunk(block, true)
+}
+
+/// Random-access reader over a complete BGZF file held in memory.
+///
+/// Positions are virtual offsets; reading continues across block boundaries.
+pub struct BgzfReader<'a> {
+    data: &'a [u8],
+    /// Compressed offset of the current block.
+    block_offset: usize,
+    /// Compressed length of the current block (0 before the first load).
+    block_len: usize,
+    /// Decompressed contents of the current block.
+    block: Vec<u8>,
+    /// Read position within `block`.
+    position: usize,
+}
+
+impl<'a> BgzfReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            block_offset: 0,
+            block_len: 0,
+            block: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Virtual offset of the next byte `read` will return.
+    pub fn virtual_offset(&self) -> u64 {
+        ((self.block_offset as u64) << 16) | self.position as u64
+    }
+
+    /// Move to a virtual offset, as found in BAM/tabix indexes.
+    pub fn seek(&mut self, virtual_offset: u64) -> Result<(), GzipError> {
+        let block_offset = (virtual_offset >> 16) as usize;
+        let position = (virtual_offset & 0xffff) as usize;
+        self.load_block(block_offset)?;
+        if position > self.block.len() {
+            return Err(GzipError::new(
+                ErrorKind::InvalidOption,
+                "BGZF virtual offset points past the end of its block",
+            ));
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    fn load_block(&mut self, block_offset: usize) -> Result<(), GzipError> {
+        let buf = self.data.get(block_offset..).unwrap_or_default();
+        let block_len = match bgzf_block_len(buf)? {
+            Some(len) if len <= buf.len() => len,
+            _ => return Err(GzipError::new(ErrorKind::UnexpectedEof, "Truncated BGZF block")),
+        };
+        self.block = decompress_bgzf_block(&buf[..block_len])?;
+        self.block_offset = block_offset;
+        self.block_len = block_len;
+        self.position = 0;
+        Ok(())
+    }
+
+    /// Read up to `buf.len()` bytes, moving on to later blocks as needed.
+    /// Returns 0 at the end of the file.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, GzipError> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.position == self.block.len() {
+                let next = self.block_offset + self.block_len;
+                if next >= self.data.len() {
+                    break;
+                }
+                self.load_block(next)?;
+                continue;
+            }
+            let n = (self.block.len() - self.position).min(buf.len() - written);
+            let end = self.position + n;
+            buf[written..written + n].copy_from_slice(&self.block[self.position..end]);
+            self.position += n;
+            written += n;
+        }
+        Ok(written)
+    }
+}
@@ -0,0 +1,248 @@
+//! Gzip member header (RFC 1952, section 2.3).
+
+use crate::GzipError;
+use core_api::ErrorKind;
+
+const ID1: u8 = 0x1f;
+const ID2: u8 = 0x8b;
+/// Compression method: deflate is the only one defined.
+const CM_DEFLATE: u8 = 8;
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+const FRESERVED: u8 = 0xe0;
+
+/// `OS` value for "unknown", written when none is set.
+pub const OS_UNKNOWN: u8 = 255;
+
+/// Metadata carried in a gzip member header.
+///
+/// Filename and comment are raw bytes (RFC 1952 specifies ISO 8859-1, most
+/// tools write UTF-8) and must not contain NUL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GzipHeader {
+    /// Original file name (`FNAME`).
+    pub filename: Option<Vec<u8>>,
+    /// Modification time in seconds since the Unix epoch; 0 means unset.
+    pub mtime: u32,
+    /// Free-form comment (`FCOMMENT`).
+    pub comment: Option<Vec<u8>>,
+    /// Extra field (`FEXTRA`) payload, at most 65535 bytes.
+    pub extra: Option<Vec<u8>>,
+    /// Operating system that wrote the file (`OS`).
+    pub os: u8,
+}
+
+impl Default for GzipHeader {
+    fn default() -> Self {
+        Self {
+            filename: None,
+            mtime: 0,
+            comment: None,
+            extra: None,
+            os: OS_UNKNOWN,
+        }
+    }
+}
+
+fn corrupt(msg: &str) -> GzipError {
+    GzipError::new(ErrorKind::CorruptInput, msg)
+}
+
+/// Longest the filename and comment may be together, terminators included.
+/// Past this a header that is still arriving is rejected rather than buffered.
+pub const MAX_TEXT_LEN: usize = 64 * 1024;
+
+/// How far `GzipHeader::parse_partial` got through a header that has not fully
+/// arrived, so the next call doesn't search the text fields again.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct HeaderScan {
+    /// Bytes already searched for a field terminator.
+    scanned: usize,
+    /// Position after the filename's terminator, once found.
+    filename_end: Option<usize>,
+}
+
+/// Find the end of the zero-terminated field starting at `pos`, searching from
+/// `from` at the earliest, and return the position after its terminator.
+fn find_cstr_end(buf: &[u8], pos: usize, from: usize) -> Option<usize> {
+    let from = from.max(pos);
+    let len = buf[from..].iter().position(|&b| b == 0)?;
+    Some(from + len + 1)
+}
+
+impl GzipHeader {
+    /// Check the fields can be encoded.
+    pub(crate) fn validate(&self) -> Result<(), GzipError> {
+        if self.filename.as_ref().is_some_and(|f| f.contains(&0)) {
+            return Err(GzipError::new(
+                ErrorKind::InvalidOption,
+                "Gzip header filename contains a NUL byte",
+            ));
+        }
+        if self.comment.as_ref().is_some_and(|c| c.contains(&0)) {
+            return Err(GzipError::new(
+                ErrorKind::InvalidOption,
+                "Gzip header comment contains a NUL byte",
+            ));
+        }
+        if self.extra.as_ref().is_some_and(|e| e.len() > u16::MAX as usize) {
+            return Err(GzipError::new(
+                ErrorKind::InvalidOption,
+                "Gzip header extra field exceeds 65535 bytes",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encode the header, with `xfl` as the extra flags byte.
+    pub(crate) fn write(&self, xfl: u8) -> Vec<u8> {
+        let mut flg = 0;
+        if self.extra.is_some() {
+            flg |= FEXTRA;
+        }
+        if self.filename.is_some() {
+            flg |= FNAME;
+        }
+        if self.comment.is_some() {
+            flg |= FCOMMENT;
+        }
+
+        let mut out = vec![ID1, ID2, CM_DEFLATE, flg];
+        out.extend_from_slice(&self.mtime.to_le_bytes());
+        out.push(xfl);
+        out.push(self.os);
+        if let Some(extra) = &self.extra {
+            out.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+            out.extend_from_slice(extra);
+        }
+        if let Some(filename) = &self.filename {
+            out.extend_from_slice(filename);
+            out.push(0);
+        }
+        if let Some(comment) = &self.comment {
+            out.extend_from_slice(comment);
+            out.push(0);
+        }
+        out
+    }
+
+    /// Encode the header as it would start a gzip member.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.write(0)
+    }
+
+    /// Parse the member header at the start of `buf`.
+    ///
+    /// Returns `None` until the whole header has arrived, otherwise the header
+    /// and its length in bytes. A filename and comment longer than
+    /// `MAX_TEXT_LEN` together are rejected as corrupt.
+    pub fn parse(buf: &[u8]) -> Result<Option<(Self, usize)>, GzipError> {
+        Self::parse_partial(buf, &mut HeaderScan::default())
+    }
+
+    /// `parse` for a header arriving in pieces: `scan` carries over from the
+    /// previous call on the same (grown) buffer.
+    pub(crate) fn parse_partial(
+        buf: &[u8],
+        scan: &mut HeaderScan,
+    ) -> Result<Option<(Self, usize)>, GzipError> {
+        if buf.len() >= 2 && (buf[0] != ID1 || buf[1] != ID2) {
+            return Err(corrupt("Invalid gzip header magic"));
+        }
+        if buf.len() < 10 {
+            return Ok(None);
+        }
+        if buf[2] != CM_DEFLATE {
+            return Err(corrupt("Unsupported gzip compression method"));
+        }
+        let flg = buf[3];
+        if flg & FRESERVED != 0 {
+            return Err(corrupt("Reserved bits set in gzip header flags"));
+        }
+
+        let mut pos = 10;
+        let mut extra = None;
+        if flg & FEXTRA != 0 {
+            let Some(len) = buf.get(pos..pos + 2) else {
+                return Ok(None);
+            };
+            let end = pos + 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+            if buf.len() < end {
+                return Ok(None);
+            }
+            extra = Some(pos + 2..end);
+            pos = end;
+        }
+
+        // The text fields are only copied out once the whole header is here,
+        // and the search for their terminators picks up where it left off.
+        let text_start = pos;
+        let mut filename = None;
+        if flg & FNAME != 0 {
+            let end = match scan.filename_end {
+                Some(end) => end,
+                None => match find_cstr_end(buf, pos, scan.scanned) {
+                    Some(end) => end,
+                    None => return scan.incomplete(buf, text_start),
+                },
+            };
+            scan.filename_end = Some(end);
+            filename = Some(pos..end - 1);
+            pos = end;
+        }
+        let mut comment = None;
+        if flg & FCOMMENT != 0 {
+            let Some(end) = find_cstr_end(buf, pos, scan.scanned) else {
+                return scan.incomplete(buf, text_start);
+            };
+            comment = Some(pos..end - 1);
+            pos = end;
+        }
+        if pos - text_start > MAX_TEXT_LEN {
+            return Err(text_too_long());
+        }
+        if flg & FHCRC != 0 {
+            let Some(crc) = buf.get(pos..pos + 2) else {
+                return Ok(None);
+            };
+            // The header CRC is the low 16 bits of the CRC-32 of everything before it.
+            let mut expected = flate2::Crc::new();
+            expected.update(&buf[..pos]);
+            if u16::from_le_bytes([crc[0], crc[1]]) != expected.sum() as u16 {
+                return Err(GzipError::new(ErrorKind::ChecksumMismatch, "Gzip header CRC mismatch"));
+            }
+            pos += 2;
+        }
+        let header = GzipHeader {
+            filename: filename.map(|r| buf[r].to_vec()),
+            mtime: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            comment: comment.map(|r| buf[r].to_vec()),
+            extra: extra.map(|r| buf[r].to_vec()),
+            os: buf[9],
+        };
+        Ok(Some((header, pos)))
+    }
+}
+
+fn text_too_long() -> GzipError {
+    corrupt("Gzip header filename and comment exceed 65536 bytes")
+}
+
+impl HeaderScan {
+    /// Note that `buf` has been searched to its end without finding the
+    /// terminator it needs, failing once the text fields outgrow their cap.
+    fn incomplete(
+        &mut self,
+        buf: &[u8],
+        text_start: usize,
+    ) -> Result<Option<(GzipHeader, usize)>, GzipError> {
+        if buf.len() - text_start > MAX_TEXT_LEN {
+            return Err(text_too_long());
+        }
+        self.scanned = buf.len();
+        Ok(None)
+    }
+}
@@ -1,54 +1,273 @@
-use core_api::{CompressionOptions, Compressor, Flush};
-use flate2::Compression;
-use std::io::Write;
+use core_api::{
+    CodecError, CompressionOptions, Compressor, DecompressionLimits, DecompressionOptions,
+    Decompressor, ErrorKind, Flush, HasErrorKind, LimitExceeded,
+};
+use flate2::{Crc, FlushDecompress, Status};
+use zlib_rs::{DeflateConfig, DeflateFlush};
+
+mod bgzf;
+mod header;
+
+pub use bgzf::{bgzf_block_len, decompress_bgzf_block, BgzfReader, BGZF_BLOCK_SIZE, BGZF_EOF};
+pub use header::{GzipHeader, MAX_TEXT_LEN, OS_UNKNOWN};
+use header::HeaderScan;
 
 /// Error type for gzip compression.
 #[derive(Debug)]
 pub enum GzipError {
     Io(std::io::Error),
-    Other(String),
+    /// `window_log` outside the 9..=15 range deflate supports.
+    InvalidWindowLog(u32),
+    /// Decompressed output ran past a `DecompressionOptions` limit.
+    LimitExceeded(LimitExceeded),
+    /// Any other failure, with its kind.
+    Codec(CodecError),
 }
 
 impl std::fmt::Display for GzipError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             GzipError::Io(e) => write!(f, "IO error: {}", e),
-            GzipError::Other(msg) => write!(f, "{}", msg),
+            GzipError::InvalidWindowLog(w) => {
+                write!(f, "Invalid deflate window log {} (expected 9..=15)", w)
+            }
+            GzipError::LimitExceeded(e) => write!(f, "{}", e),
+            GzipError::Codec(e) => write!(f, "{}", e),
         }
     }
 }
 
 impl std::error::Error for GzipError {}
 
+impl GzipError {
+    /// A `Codec` error of `kind`.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        GzipError::Codec(CodecError::new(kind, message))
+    }
+}
+
+impl HasErrorKind for GzipError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            GzipError::Io(e) => ErrorKind::from_io(e),
+            GzipError::InvalidWindowLog(_) => ErrorKind::InvalidOption,
+            GzipError::LimitExceeded(_) => ErrorKind::LimitExceeded,
+            GzipError::Codec(e) => e.kind(),
+        }
+    }
+}
+
 impl From<std::io::Error> for GzipError {
     fn from(e: std::io::Error) -> Self {
         GzipError::Io(e)
     }
 }
 
+impl From<LimitExceeded> for GzipError {
+    fn from(e: LimitExceeded) -> Self {
+        GzipError::LimitExceeded(e)
+    }
+}
+
+/// Container wrapped around the deflate stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GzipFormat {
+    /// Gzip (RFC 1952): `Content-Encoding: gzip`, `.gz` files.
+    #[default]
+    Gzip,
+    /// Zlib (RFC 1950): `Content-Encoding: deflate`, PNG and PDF streams.
+    Zlib,
+    /// Raw deflate (RFC 1951) with no header or trailer: WebSocket
+    /// permessage-deflate, ZIP entries.
+    Raw,
+    /// BGZF: gzip in independent blocks of at most 64 KiB, as used by BAM
+    /// and `.vcf.gz`. Decodes like multi-member gzip.
+    Bgzf,
+}
+
+impl GzipFormat {
+    /// Whether the deflate data sits in gzip members whose header and
+    /// trailer this crate reads and writes.
+    fn is_gzip(self) -> bool {
+        matches!(self, GzipFormat::Gzip | GzipFormat::Bgzf)
+    }
+}
+
+/// Deflate strategy (zlib's `Z_*` strategies): trades match finding for
+/// speed on data where it doesn't pay off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeflateStrategy {
+    #[default]
+    Default,
+    /// Favour Huffman coding over short matches, for filtered data such as
+    /// PNG scanlines.
+    Filtered,
+    /// No string matching at all.
+    HuffmanOnly,
+    /// Only matches at distance 1 (run-length encoding).
+    Rle,
+    /// Fixed Huffman codes instead of per-block dynamic ones.
+    Fixed,
+}
+
+impl From<DeflateStrategy> for zlib_rs::Strategy {
+    fn from(strategy: DeflateStrategy) -> Self {
+        match strategy {
+            DeflateStrategy::Default => zlib_rs::Strategy::Default,
+            DeflateStrategy::Filtered => zlib_rs::Strategy::Filtered,
+            DeflateStrategy::HuffmanOnly => zlib_rs::Strategy::HuffmanOnly,
+            DeflateStrategy::Rle => zlib_rs::Strategy::Rle,
+            DeflateStrategy::Fixed => zlib_rs::Strategy::Fixed,
+        }
+    }
+}
+
+/// Gzip-specific compressor settings.
+#[derive(Debug, Clone, Default)]
+pub struct GzipOptions {
+    pub format: GzipFormat,
+    pub strategy: DeflateStrategy,
+    /// Header written at the start of a `GzipFormat::Gzip` stream; ignored by
+    /// the other containers.
+    pub header: GzipHeader,
+    /// Preset dictionary (zlib's `deflateSetDictionary`) the stream may
+    /// reference from its first byte. Only `GzipFormat::Zlib` and
+    /// `GzipFormat::Raw` can carry one; the decompressor needs the same bytes.
+    pub dictionary: Option<Vec<u8>>,
+}
+
 /// Gzip/deflate compressor.
+///
+/// Drives a zlib-rs deflate stream so each call can pick its own flush mode.
+/// Zlib writes its own container; the gzip header and trailer are written
+/// here so the header fields can be set.
 pub struct GzipCompressor {
-    encoder: flate2::write::GzEncoder<Vec<u8>>,
+    compress: zlib_rs::Deflate,
+    format: GzipFormat,
+    /// Gzip header not yet emitted.
+    header: Vec<u8>,
+    /// CRC of the uncompressed input, for the gzip trailer.
+    crc: Option<Crc>,
+    /// Input of the BGZF block being filled.
+    block: Vec<u8>,
     finished: bool,
 }
 
-impl Compressor for GzipCompressor {
-    type Error = GzipError;
+impl GzipCompressor {
+    /// Run `input` through the deflate stream with the given flush mode and
+    /// collect everything it produces.
+    fn deflate(&mut self, input: &[u8], flush: DeflateFlush) -> Result<Vec<u8>, GzipError> {
+        let mut output = Vec::with_capacity(input.len() / 2 + 64);
+        let mut consumed = 0;
+        loop {
+            if output.capacity() - output.len() < 64 {
+                output.reserve(output.capacity().max(4096));
+            }
+            let before_in = self.compress.total_in();
+            let before_out = self.compress.total_out();
+            let len = output.len();
+            output.resize(output.capacity(), 0);
+            let result = self.compress.compress(&input[consumed..], &mut output[len..], flush);
+            output.truncate(len + (self.compress.total_out() - before_out) as usize);
+            let status = result.map_err(|e| GzipError::new(ErrorKind::InvalidOption, e.as_str()))?;
+            consumed += (self.compress.total_in() - before_in) as usize;
 
-    fn new(options: CompressionOptions) -> Result<Self, Self::Error> {
-        let level = options.level.unwrap_or(6);
-        let compression = match level {
-            0 => Compression::none(),
-            l if l <= 9 => Compression::new(l as u32),
-            _ => Compression::best(),
+            match status {
+                zlib_rs::Status::StreamEnd | zlib_rs::Status::BufError => break,
+                // Spare output space means deflate has nothing more to emit for this flush mode.
+                zlib_rs::Status::Ok => {
+                    if consumed == input.len()
+                        && output.len() < output.capacity()
+                        && flush != DeflateFlush::Finish
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Create a compressor that writes the given container format.
+    pub fn with_format(options: CompressionOptions, format: GzipFormat) -> Result<Self, GzipError> {
+        Self::with_options(
+            options,
+            GzipOptions {
+                format,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Create a compressor with gzip-specific settings.
+    pub fn with_options(options: CompressionOptions, gzip: GzipOptions) -> Result<Self, GzipError> {
+        let level = options.level.unwrap_or(6).min(9);
+
+        let window_bits = match options.window_log {
+            None => 15,
+            Some(w @ 9..=15) => w as i32,
+            Some(w) => return Err(GzipError::InvalidWindowLog(w)),
+        };
+
+        // Positive window bits select the zlib container, negative raw deflate.
+        let window_bits = if gzip.format == GzipFormat::Zlib {
+            window_bits
+        } else {
+            -window_bits
+        };
+        let mut compress = zlib_rs::Deflate::new_with_config(DeflateConfig {
+            level: level as i32,
+            window_bits,
+            strategy: gzip.strategy.into(),
+            ..Default::default()
+        });
+        if let Some(dictionary) = &gzip.dictionary {
+            if gzip.format.is_gzip() {
+                return Err(GzipError::new(
+                    ErrorKind::InvalidOption,
+                    "Preset dictionaries need the zlib or raw format",
+                ));
+            }
+            compress
+                .set_dictionary(dictionary)
+                .map_err(|e| GzipError::new(ErrorKind::InvalidOption, e.as_str()))?;
+        }
+
+        let (header, crc) = if gzip.format == GzipFormat::Gzip {
+            gzip.header.validate()?;
+            // XFL flags the slowest and fastest settings, as zlib does.
+            let fast_strategy = matches!(
+                gzip.strategy,
+                DeflateStrategy::HuffmanOnly | DeflateStrategy::Rle | DeflateStrategy::Fixed
+            );
+            let xfl = match level {
+                9 => 2,
+                0 | 1 => 4,
+                _ if fast_strategy => 4,
+                _ => 0,
+            };
+            (gzip.header.write(xfl), Some(Crc::new()))
+        } else {
+            (Vec::new(), None)
         };
 
-        let encoder = flate2::write::GzEncoder::new(Vec::new(), compression);
         Ok(Self {
-            encoder,
+            compress,
+            format: gzip.format,
+            header,
+            crc,
+            block: Vec::new(),
             finished: false,
         })
     }
+}
+
+impl Compressor for GzipCompressor {
+    type Error = GzipError;
+
+    fn new(options: CompressionOptions) -> Result<Self, Self::Error> {
+        Self::with_format(options, GzipFormat::Gzip)
+    }
 
     fn compress_chunk(
         &mut self,
@@ -56,42 +275,419 @@ impl Compressor for GzipCompressor {
         flush: Flush,
     ) -> Result<Vec<u8>, Self::Error> {
         if self.finished {
-            return Err(GzipError::Other("Cannot compress after finish".to_string()));
+            return Err(GzipError::new(ErrorKind::UsedAfterFinish, "Cannot compress after finish"));
         }
 
-        self.encoder.write_all(input)?;
+        let mode = match flush {
+            Flush::None => DeflateFlush::NoFlush,
+            Flush::Sync => DeflateFlush::SyncFlush,
+            Flush::Full => DeflateFlush::FullFlush,
+            Flush::Finish => {
+                self.finished = true;
+                DeflateFlush::Finish
+            }
+        };
+        if self.format == GzipFormat::Bgzf {
+            return self.compress_bgzf(input, flush);
+        }
 
-        if matches!(flush, Flush::Finish) {
-            self.finished = true;
-            // finish() takes ownership, so we need to replace with a dummy encoder
-            let encoder = std::mem::replace(
-                &mut self.encoder,
-                flate2::write::GzEncoder::new(Vec::new(), Compression::default()),
-            );
-            encoder.finish().map_err(GzipError::from)
-        } else {
-            self.encoder.flush()?;
-            // Take the output from the inner Vec<u8>
-            let mut output = Vec::new();
-            std::mem::swap(self.encoder.get_mut(), &mut output);
-            Ok(output)
+        let mut output = std::mem::take(&mut self.header);
+        output.append(&mut self.deflate(input, mode)?);
+        if let Some(crc) = &mut self.crc {
+            crc.update(input);
+            if flush == Flush::Finish {
+                output.extend_from_slice(&crc.sum().to_le_bytes());
+                output.extend_from_slice(&crc.amount().to_le_bytes());
+            }
         }
+        Ok(output)
     }
 
     fn compress_all(
         input: &[u8],
         options: CompressionOptions,
     ) -> Result<Vec<u8>, Self::Error> {
-        let level = options.level.unwrap_or(6);
-        let compression = match level {
-            0 => Compression::none(),
-            l if l <= 9 => Compression::new(l as u32),
-            _ => Compression::best(),
+        let mut compressor = Self::new(options)?;
+        compressor.compress_chunk(input, Flush::Finish)
+    }
+}
+
+/// Gzip-specific decompressor settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GzipDecompressOptions {
+    pub format: GzipFormat,
+    /// Decode every member of a concatenated gzip stream (`cat a.gz b.gz`).
+    /// When false, decoding stops after the first member and anything that
+    /// follows it is ignored. Only applies to `GzipFormat::Gzip` and
+    /// `GzipFormat::Bgzf`.
+    pub multi_member: bool,
+    /// Preset dictionary the stream was compressed with. Raw deflate starts
+    /// from it; zlib streams name their dictionary by Adler-32, which must
+    /// match. Ignored by the gzip containers.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+impl Default for GzipDecompressOptions {
+    fn default() -> Self {
+        Self {
+            format: GzipFormat::Gzip,
+            multi_member: true,
+            dictionary: None,
+        }
+    }
+}
+
+/// Position of one decoded gzip member in the compressed and decompressed streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GzipMember {
+    pub header: GzipHeader,
+    /// Offset of the member's first byte in the compressed input.
+    pub compressed_offset: u64,
+    /// Length of the member, header and trailer included.
+    pub compressed_len: u64,
+    /// Offset of the member's first decompressed byte in the output.
+    pub output_offset: u64,
+    pub output_len: u64,
+}
+
+/// Where the decompressor is in the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    /// Collecting the header of the next gzip member.
+    Header,
+    /// Inflating the deflate stream.
+    Body,
+    /// Collecting the 8-byte gzip trailer (CRC-32 and ISIZE).
+    Trailer,
+    /// Stream complete.
+    Done,
+}
+
+/// Gzip/deflate decompressor.
+///
+/// Drives a raw `flate2::Decompress` stream, so each call returns whatever
+/// output the bytes received so far allow. Zlib checks its own container;
+/// the gzip header and trailer are parsed here so headers and member
+/// boundaries can be returned.
+pub struct GzipDecompressor {
+    decompress: flate2::Decompress,
+    limits: DecompressionLimits,
+    format: GzipFormat,
+    multi_member: bool,
+    stage: Stage,
+    /// Partial header or trailer bytes received so far.
+    buffer: Vec<u8>,
+    /// Progress through the partial header in `buffer`.
+    header_scan: HeaderScan,
+    /// Header of the first member.
+    header: Option<GzipHeader>,
+    /// Header of the member being decoded.
+    member_header: Option<GzipHeader>,
+    /// Compressed and decompressed offsets where the current member starts.
+    member_start: (u64, u64),
+    /// Completed members not yet collected by `take_members`.
+    members: Vec<GzipMember>,
+    /// Preset dictionary, supplied when a zlib stream asks for it.
+    dictionary: Option<Vec<u8>>,
+    total_in: u64,
+    total_out: u64,
+    crc: Crc,
+    finished: bool,
+}
+
+impl GzipDecompressor {
+    /// Create a decompressor that reads the given container format.
+    pub fn with_format(
+        options: DecompressionOptions,
+        format: GzipFormat,
+    ) -> Result<Self, GzipError> {
+        Self::with_options(
+            options,
+            GzipDecompressOptions {
+                format,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Create a decompressor with gzip-specific settings.
+    pub fn with_options(
+        options: DecompressionOptions,
+        gzip: GzipDecompressOptions,
+    ) -> Result<Self, GzipError> {
+        let format = gzip.format;
+        let mut decompress =
+            flate2::Decompress::new_with_window_bits(format == GzipFormat::Zlib, 15);
+        // Raw deflate has no header to announce a dictionary, so it is set up front.
+        if let (GzipFormat::Raw, Some(dictionary)) = (format, &gzip.dictionary) {
+            decompress
+                .set_dictionary(dictionary)
+                .map_err(|e| GzipError::new(ErrorKind::CorruptInput, e.to_string()))?;
+        }
+        let stage = if format.is_gzip() {
+            Stage::Header
+        } else {
+            Stage::Body
         };
+        Ok(Self {
+            decompress,
+            limits: DecompressionLimits::new(options),
+            format,
+            multi_member: gzip.multi_member,
+            stage,
+            buffer: Vec::new(),
+            header_scan: HeaderScan::default(),
+            header: None,
+            member_header: None,
+            member_start: (0, 0),
+            members: Vec::new(),
+            dictionary: gzip.dictionary,
+            total_in: 0,
+            total_out: 0,
+            crc: Crc::new(),
+            finished: false,
+        })
+    }
+
+    /// Decompress a complete stream, also returning the boundaries of each
+    /// gzip member.
+    pub fn decompress_with_members(
+        input: &[u8],
+        options: DecompressionOptions,
+        gzip: GzipDecompressOptions,
+    ) -> Result<(Vec<u8>, Vec<GzipMember>), GzipError> {
+        let mut decoder = Self::with_options(options, gzip)?;
+        let output = decoder.decompress_chunk(input, true)?;
+        Ok((output, decoder.take_members()))
+    }
+
+    /// The header of the first gzip member, once it has been received.
+    pub fn header(&self) -> Option<&GzipHeader> {
+        self.header.as_ref()
+    }
+
+    /// Members completed since the last call, in stream order.
+    pub fn take_members(&mut self) -> Vec<GzipMember> {
+        std::mem::take(&mut self.members)
+    }
+
+    /// True when the input so far ends exactly at the end of the stream (or,
+    /// for multi-member gzip, at the end of a member).
+    fn is_complete(&self) -> bool {
+        match self.stage {
+            Stage::Done => true,
+            Stage::Header => self.header.is_some() && self.buffer.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Record the member that just ended at compressed offset `end` and get
+    /// ready for the next one.
+    fn end_member(&mut self, end: u64) {
+        let (compressed_offset, output_offset) = self.member_start;
+        self.members.push(GzipMember {
+            header: self.member_header.take().unwrap_or_default(),
+            compressed_offset,
+            compressed_len: end - compressed_offset,
+            output_offset,
+            output_len: self.total_out - output_offset,
+        });
+        self.member_start = (end, self.total_out);
+        if self.multi_member {
+            self.decompress.reset(false);
+            self.crc.reset();
+            self.stage = Stage::Header;
+        } else {
+            self.stage = Stage::Done;
+        }
+    }
+
+    /// Run `input` through the container stages and collect everything it produces.
+    fn inflate(&mut self, input: &[u8]) -> Result<Vec<u8>, GzipError> {
+        self.limits.add_input(input.len());
+        let mut output = Vec::with_capacity(input.len() * 2 + 64);
+        let base = self.total_in;
+        let total = input.len();
+        self.total_in += total as u64;
+        let mut input = input;
+        while !input.is_empty() {
+            match self.stage {
+                Stage::Header => {
+                    // Parse straight from the input unless an earlier call left a partial header.
+                    let pending = self.buffer.len();
+                    let parsed = if pending == 0 {
+                        GzipHeader::parse_partial(input, &mut self.header_scan)?
+                    } else {
+                        self.buffer.extend_from_slice(input);
+                        GzipHeader::parse_partial(&self.buffer, &mut self.header_scan)?
+                    };
+                    match parsed {
+                        Some((header, len)) => {
+                            input = &input[len - pending..];
+                            self.buffer.clear();
+                            self.header_scan = HeaderScan::default();
+                            if self.header.is_none() {
+                                self.header = Some(header.clone());
+                            }
+                            self.member_header = Some(header);
+                            self.stage = Stage::Body;
+                        }
+                        None => {
+                            if pending == 0 {
+                                self.buffer.extend_from_slice(input);
+                            }
+                            input = &[];
+                        }
+                    }
+                }
+                Stage::Body => {
+                    let consumed = self.inflate_body(input, &mut output)?;
+                    input = &input[consumed..];
+                    if self.stage == Stage::Body && !input.is_empty() {
+                        return Err(GzipError::new(
+                            ErrorKind::CorruptInput,
+                            "Inflate made no progress",
+                        ));
+                    }
+                }
+                Stage::Trailer => {
+                    let n = (8 - self.buffer.len()).min(input.len());
+                    self.buffer.extend_from_slice(&input[..n]);
+                    input = &input[n..];
+                    if self.buffer.len() == 8 {
+                        let crc = u32::from_le_bytes(self.buffer[..4].try_into().unwrap());
+                        let size = u32::from_le_bytes(self.buffer[4..].try_into().unwrap());
+                        if crc != self.crc.sum() {
+                            return Err(GzipError::new(
+                                ErrorKind::ChecksumMismatch,
+                                "Gzip CRC mismatch",
+                            ));
+                        }
+                        if size != self.crc.amount() {
+                            return Err(GzipError::new(
+                                ErrorKind::ChecksumMismatch,
+                                "Gzip length mismatch",
+                            ));
+                        }
+                        self.buffer.clear();
+                        self.end_member(base + (total - input.len()) as u64);
+                    }
+                }
+                // A single-member gzip read ignores whatever follows the member.
+                Stage::Done if self.format.is_gzip() => input = &[],
+                Stage::Done => {
+                    return Err(GzipError::new(
+                        ErrorKind::CorruptInput,
+                        "Trailing data after end of deflate stream",
+                    ));
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Inflate from `input` into `output` until the input runs out or the
+    /// deflate stream ends, returning the number of bytes consumed.
+    fn inflate_body(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<usize, GzipError> {
+        let start = output.len();
+        let mut consumed = 0;
+        loop {
+            if output.capacity() - output.len() < 64 {
+                let mut additional = output.capacity().max(4096);
+                // Room for one byte past the budget is enough to trip a limit.
+                if let Some(remaining) = self.limits.remaining_output() {
+                    let budget = usize::try_from(remaining.saturating_add(1)).unwrap_or(usize::MAX);
+                    additional = additional.min(budget);
+                }
+                output.reserve_exact(additional);
+            }
+            let before = self.decompress.total_in();
+            let produced = output.len();
+            let result =
+                self.decompress
+                    .decompress_vec(&input[consumed..], output, FlushDecompress::None);
+            consumed += (self.decompress.total_in() - before) as usize;
+            self.limits.add_output(output.len() - produced)?;
+            let status = match result {
+                Ok(status) => status,
+                // A zlib header with FDICT set stops inflate until the dictionary is supplied.
+                Err(e) if e.needs_dictionary().is_some() => {
+                    let dictionary = self.dictionary.as_deref().ok_or_else(|| {
+                        GzipError::new(
+                            ErrorKind::InvalidOption,
+                            "Zlib stream needs a preset dictionary",
+                        )
+                    })?;
+                    self.decompress.set_dictionary(dictionary).map_err(|_| {
+                        GzipError::new(ErrorKind::InvalidOption, "Zlib preset dictionary mismatch")
+                    })?;
+                    continue;
+                }
+                Err(e) => return Err(GzipError::new(ErrorKind::CorruptInput, e.to_string())),
+            };
+
+            match status {
+                Status::StreamEnd => {
+                    self.stage = if self.format.is_gzip() {
+                        Stage::Trailer
+                    } else {
+                        Stage::Done
+                    };
+                    break;
+                }
+                // Spare output space left over means inflate is waiting on more input.
+                Status::BufError => break,
+                Status::Ok => {
+                    if consumed == input.len() && output.len() < output.capacity() {
+                        break;
+                    }
+                }
+            }
+        }
+        self.total_out += (output.len() - start) as u64;
+        if self.format.is_gzip() {
+            self.crc.update(&output[start..]);
+        }
+        Ok(consumed)
+    }
+}
+
+impl Decompressor for GzipDecompressor {
+    type Error = GzipError;
+
+    fn new(options: DecompressionOptions) -> Result<Self, Self::Error> {
+        Self::with_format(options, GzipFormat::Gzip)
+    }
+
+    fn decompress_chunk(
+        &mut self,
+        input: &[u8],
+        finish: bool,
+    ) -> Result<Vec<u8>, Self::Error> {
+        if self.finished {
+            return Err(GzipError::new(
+                ErrorKind::UsedAfterFinish,
+                "Cannot decompress after finish",
+            ));
+        }
+
+        let output = self.inflate(input)?;
+
+        if finish {
+            self.finished = true;
+            if !self.is_complete() {
+                return Err(GzipError::new(ErrorKind::UnexpectedEof, "Truncated deflate stream"));
+            }
+        }
+        Ok(output)
+    }
 
-        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), compression);
-        encoder.write_all(input)?;
-        encoder.finish().map_err(GzipError::from)
+    fn decompress_all(
+        input: &[u8],
+        options: DecompressionOptions,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let mut decoder = Self::new(options)?;
+        decoder.decompress_chunk(input, true)
     }
 }
 
@@ -104,21 +700,450 @@ mod tests {
         let input = b"hello world";
         let options = CompressionOptions::default();
         let compressed = GzipCompressor::compress_all(input, options).unwrap();
-        assert!(!compressed.is_empty());
         // For very small inputs, compressed size may be larger due to headers
         // Just verify we got valid compressed output
-        assert!(compressed.len() > 0);
+        assert!(!compressed.is_empty());
     }
 
     #[test]
     fn test_streaming() {
         let mut compressor = GzipCompressor::new(CompressionOptions::default()).unwrap();
         let chunk1 = compressor.compress_chunk(b"hello ", Flush::None).unwrap();
-        // With the flush optimization, we should get some data out even for small inputs
-        // Gzip header is at least 10 bytes
-        assert!(!chunk1.is_empty());
+        // The gzip header is emitted as soon as the stream starts (at least 10 bytes)
+        assert!(chunk1.len() >= 10);
         let chunk2 = compressor.compress_chunk(b"world", Flush::Finish).unwrap();
         assert!(!chunk2.is_empty());
     }
-}
 
+    #[test]
+    fn test_sync_and_full_flush() {
+        let mut compressor = GzipCompressor::new(CompressionOptions::default()).unwrap();
+        let mut decompressor = GzipDecompressor::new(DecompressionOptions::default()).unwrap();
+
+        let out = compressor.compress_chunk(b"hello ", Flush::Sync).unwrap();
+        // Sync flush ends on the empty stored block marker
+        assert!(out.ends_with(&[0x00, 0x00, 0xff, 0xff]));
+        assert_eq!(decompressor.decompress_chunk(&out, false).unwrap(), b"hello ");
+
+        let out = compressor.compress_chunk(b"world", Flush::Full).unwrap();
+        assert!(out.ends_with(&[0x00, 0x00, 0xff, 0xff]));
+        assert_eq!(decompressor.decompress_chunk(&out, false).unwrap(), b"world");
+
+        let out = compressor.compress_chunk(b"", Flush::Finish).unwrap();
+        assert!(decompressor.decompress_chunk(&out, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_window_log() {
+        let input = b"window window window window".repeat(64);
+        for window_log in [9, 12, 15] {
+            let options = CompressionOptions {
+                window_log: Some(window_log),
+                ..Default::default()
+            };
+            let compressed = GzipCompressor::compress_all(&input, options).unwrap();
+            let output =
+                GzipDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+            assert_eq!(output.unwrap(), input);
+        }
+
+        for window_log in [8, 16] {
+            let options = CompressionOptions {
+                window_log: Some(window_log),
+                ..Default::default()
+            };
+            assert!(matches!(
+                GzipCompressor::new(options),
+                Err(GzipError::InvalidWindowLog(w)) if w == window_log
+            ));
+        }
+    }
+
+    #[test]
+    fn test_strategies() {
+        let input = b"abcabcabc aaaaaaaaaaaaaaaa scanline scanline".repeat(200);
+        let compress = |strategy| {
+            let gzip = GzipOptions {
+                strategy,
+                ..Default::default()
+            };
+            let mut compressor =
+                GzipCompressor::with_options(CompressionOptions::default(), gzip).unwrap();
+            compressor.compress_chunk(&input, Flush::Finish).unwrap()
+        };
+
+        let default = compress(DeflateStrategy::Default);
+        for strategy in [
+            DeflateStrategy::Filtered,
+            DeflateStrategy::HuffmanOnly,
+            DeflateStrategy::Rle,
+            DeflateStrategy::Fixed,
+        ] {
+            let compressed = compress(strategy);
+            let output = GzipDecompressor::decompress_all(&compressed, Default::default());
+            assert_eq!(output.unwrap(), input);
+            if strategy == DeflateStrategy::HuffmanOnly {
+                // No string matching: repetitive input barely shrinks.
+                assert!(compressed.len() > default.len() * 10);
+                assert_eq!(compressed[8], 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let dictionary = b"{\"name\": \"\", \"email\": \"\", \"verified\": false}".to_vec();
+        let input = b"{\"name\": \"ada\", \"email\": \"ada@example.com\", \"verified\": true}";
+        let compress = |format, dictionary: Option<Vec<u8>>| {
+            let gzip = GzipOptions {
+                format,
+                dictionary,
+                ..Default::default()
+            };
+            let mut compressor =
+                GzipCompressor::with_options(CompressionOptions::default(), gzip).unwrap();
+            compressor.compress_chunk(input, Flush::Finish).unwrap()
+        };
+        let decompress = |compressed: &[u8], format, dictionary: Option<Vec<u8>>| {
+            let gzip = GzipDecompressOptions {
+                format,
+                dictionary,
+                ..Default::default()
+            };
+            let mut decoder =
+                GzipDecompressor::with_options(DecompressionOptions::default(), gzip).unwrap();
+            decoder.decompress_chunk(compressed, true)
+        };
+
+        for format in [GzipFormat::Zlib, GzipFormat::Raw] {
+            let plain = compress(format, None);
+            let compressed = compress(format, Some(dictionary.clone()));
+            assert!(compressed.len() < plain.len());
+            let output = decompress(&compressed, format, Some(dictionary.clone()));
+            assert_eq!(output.unwrap(), input);
+        }
+
+        // Zlib sets FDICT and names the dictionary, so a missing or wrong one is caught.
+        let compressed = compress(GzipFormat::Zlib, Some(dictionary.clone()));
+        assert_ne!(compressed[1] & 0x20, 0);
+        assert!(decompress(&compressed, GzipFormat::Zlib, None).is_err());
+        assert!(decompress(&compressed, GzipFormat::Zlib, Some(b"other".to_vec())).is_err());
+
+        let gzip = GzipOptions {
+            dictionary: Some(dictionary),
+            ..Default::default()
+        };
+        assert!(GzipCompressor::with_options(CompressionOptions::default(), gzip).is_err());
+    }
+
+    #[test]
+    fn test_decompress_roundtrip() {
+        let input = b"hello hello hello world";
+        let compressed =
+            GzipCompressor::compress_all(input, CompressionOptions::default()).unwrap();
+        let output = GzipDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), input);
+
+        let mut decompressor = GzipDecompressor::new(DecompressionOptions::default()).unwrap();
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+        let mut output = decompressor.decompress_chunk(head, false).unwrap();
+        output.extend(decompressor.decompress_chunk(tail, true).unwrap());
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_formats() {
+        let input = b"container container container".repeat(16);
+        let formats = [GzipFormat::Gzip, GzipFormat::Zlib, GzipFormat::Raw, GzipFormat::Bgzf];
+        for format in formats {
+            let mut compressor =
+                GzipCompressor::with_format(CompressionOptions::default(), format).unwrap();
+            let compressed = compressor.compress_chunk(&input, Flush::Finish).unwrap();
+            match format {
+                GzipFormat::Gzip => assert_eq!(&compressed[..2], &[0x1f, 0x8b]),
+                // CMF for deflate with a 32K window
+                GzipFormat::Zlib => assert_eq!(compressed[0], 0x78),
+                GzipFormat::Raw => assert_ne!(&compressed[..2], &[0x1f, 0x8b]),
+                // Gzip with FEXTRA set for the BC subfield
+                GzipFormat::Bgzf => assert_eq!(&compressed[..4], &[0x1f, 0x8b, 8, 4]),
+            }
+
+            let mut decompressor =
+                GzipDecompressor::with_format(DecompressionOptions::default(), format).unwrap();
+            assert_eq!(decompressor.decompress_chunk(&compressed, true).unwrap(), input);
+
+            // The other containers reject it.
+            if format != GzipFormat::Raw {
+                let options = DecompressionOptions::default();
+                assert!(GzipDecompressor::with_format(options, GzipFormat::Raw)
+                    .unwrap()
+                    .decompress_chunk(&compressed, true)
+                    .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_header_fields() {
+        let header = GzipHeader {
+            filename: Some(b"report.csv".to_vec()),
+            mtime: 1_700_000_000,
+            comment: Some(b"nightly export".to_vec()),
+            extra: Some(b"AB\x02\x00hi".to_vec()),
+            os: 3,
+        };
+        let gzip = GzipOptions {
+            header: header.clone(),
+            ..Default::default()
+        };
+        let mut compressor =
+            GzipCompressor::with_options(CompressionOptions::default(), gzip).unwrap();
+        let compressed = compressor.compress_chunk(b"a,b,c\n1,2,3\n", Flush::Finish).unwrap();
+        assert!(compressed.starts_with(&header.to_bytes()[..8]));
+
+        // Feed one byte at a time so the header arrives in pieces.
+        let mut decompressor = GzipDecompressor::new(DecompressionOptions::default()).unwrap();
+        let mut output = Vec::new();
+        for byte in compressed.chunks(1) {
+            output.extend(decompressor.decompress_chunk(byte, false).unwrap());
+        }
+        output.extend(decompressor.decompress_chunk(&[], true).unwrap());
+        assert_eq!(output, b"a,b,c\n1,2,3\n");
+        assert_eq!(decompressor.header(), Some(&header));
+
+        // A bare header when nothing is set.
+        let compressed =
+            GzipCompressor::compress_all(b"x", CompressionOptions::default()).unwrap();
+        assert_eq!(compressed[3], 0);
+        assert_eq!(compressed[9], OS_UNKNOWN);
+
+        let invalid = GzipOptions {
+            header: GzipHeader {
+                filename: Some(b"a\0b".to_vec()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(GzipCompressor::with_options(CompressionOptions::default(), invalid).is_err());
+    }
+
+    #[test]
+    fn test_header_crc_and_trailer() {
+        // Hand-built member whose header carries the optional FHCRC.
+        let mut stream = vec![0x1f, 0x8b, 8, 0x02, 0, 0, 0, 0, 0, 3];
+        let mut crc = Crc::new();
+        crc.update(&stream);
+        stream.extend_from_slice(&(crc.sum() as u16).to_le_bytes());
+        let body = GzipCompressor::with_format(CompressionOptions::default(), GzipFormat::Raw)
+            .unwrap()
+            .compress_chunk(b"checked", Flush::Finish)
+            .unwrap();
+        stream.extend_from_slice(&body);
+        let mut crc = Crc::new();
+        crc.update(b"checked");
+        stream.extend_from_slice(&crc.sum().to_le_bytes());
+        stream.extend_from_slice(&crc.amount().to_le_bytes());
+
+        let output = GzipDecompressor::decompress_all(&stream, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), b"checked");
+
+        let mut bad_header = stream.clone();
+        bad_header[10] ^= 0xff;
+        assert!(GzipDecompressor::decompress_all(&bad_header, Default::default()).is_err());
+
+        let mut bad_crc = stream.clone();
+        let len = bad_crc.len();
+        bad_crc[len - 8] ^= 0xff;
+        assert!(GzipDecompressor::decompress_all(&bad_crc, Default::default()).is_err());
+    }
+
+    #[test]
+    fn test_header_too_long() {
+        // A filename that never ends is rejected once it passes the cap,
+        // however it is split up.
+        let mut decompressor = GzipDecompressor::new(DecompressionOptions::default()).unwrap();
+        decompressor
+            .decompress_chunk(&[0x1f, 0x8b, 8, 0x08, 0, 0, 0, 0, 0, 3], false)
+            .unwrap();
+        let chunk = [b'a'; 1000];
+        let mut result = Ok(Vec::new());
+        for _ in 0..=MAX_TEXT_LEN / chunk.len() {
+            result = decompressor.decompress_chunk(&chunk, false);
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::CorruptInput);
+
+        // Filename and comment count together.
+        let mut stream = vec![0x1f, 0x8b, 8, 0x18, 0, 0, 0, 0, 0, 3];
+        stream.extend_from_slice(&[b'n'; MAX_TEXT_LEN / 2]);
+        stream.push(0);
+        stream.extend_from_slice(&[b'c'; MAX_TEXT_LEN / 2]);
+        stream.push(0);
+        let err = GzipHeader::parse(&stream).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::CorruptInput);
+        stream.truncate(stream.len() - 3);
+        stream.push(0);
+        assert!(GzipHeader::parse(&stream).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_multi_member() {
+        let first = GzipCompressor::compress_all(b"first member\n", Default::default()).unwrap();
+        let second = GzipCompressor::compress_all(b"second\n", Default::default()).unwrap();
+        let stream = [first.clone(), second.clone()].concat();
+
+        let (output, members) = GzipDecompressor::decompress_with_members(
+            &stream,
+            DecompressionOptions::default(),
+            GzipDecompressOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(output, b"first member\nsecond\n");
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[1].compressed_offset, first.len() as u64);
+        assert_eq!(members[1].compressed_len, second.len() as u64);
+        assert_eq!(members[1].output_offset, 13);
+        assert_eq!(members[1].output_len, 7);
+
+        // Streaming reports each member as its trailer arrives.
+        let mut decompressor = GzipDecompressor::new(DecompressionOptions::default()).unwrap();
+        let mut boundaries = Vec::new();
+        for byte in stream.chunks(1) {
+            decompressor.decompress_chunk(byte, false).unwrap();
+            boundaries.extend(decompressor.take_members().iter().map(|m| m.compressed_offset));
+        }
+        decompressor.decompress_chunk(&[], true).unwrap();
+        assert_eq!(boundaries, [0, first.len() as u64]);
+
+        // Opting out stops after the first member.
+        let single = GzipDecompressOptions {
+            multi_member: false,
+            ..Default::default()
+        };
+        let (output, members) =
+            GzipDecompressor::decompress_with_members(&stream, Default::default(), single).unwrap();
+        assert_eq!(output, b"first member\n");
+        assert_eq!(members.len(), 1);
+
+        // A member cut short is still an error.
+        let truncated = &stream[..stream.len() - 3];
+        assert!(GzipDecompressor::decompress_all(truncated, Default::default()).is_err());
+    }
+
+    #[test]
+    fn test_bgzf() {
+        // Text followed by incompressible bytes, so some blocks are stored.
+        let mut input = b"chr1\t12345\t.\tA\tG\n".repeat(8000);
+        let mut state = 0x2545_f491u32;
+        input.extend((0..100_000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        }));
+
+        let mut compressor =
+            GzipCompressor::with_format(Default::default(), GzipFormat::Bgzf).unwrap();
+        let mut compressed = Vec::new();
+        for chunk in input.chunks(30_000) {
+            compressed.extend(compressor.compress_chunk(chunk, Flush::None).unwrap());
+        }
+        compressed.extend(compressor.compress_chunk(&[], Flush::Finish).unwrap());
+        assert!(compressed.ends_with(&BGZF_EOF));
+
+        // Walk the blocks by their BSIZE, noting where each starts.
+        let mut starts = Vec::new();
+        let mut offset = 0;
+        while offset < compressed.len() {
+            let len = bgzf_block_len(&compressed[offset..]).unwrap().unwrap();
+            assert!(len <= 0x10000);
+            starts.push(offset);
+            offset += len;
+        }
+        assert_eq!(offset, compressed.len());
+        assert_eq!(starts.len(), input.len().div_ceil(BGZF_BLOCK_SIZE) + 1);
+
+        // Plain multi-member gzip decoding reads it back.
+        let output = GzipDecompressor::decompress_all(&compressed, Default::default()).unwrap();
+        assert_eq!(output, input);
+
+        // Random access from a virtual offset, reading across a block boundary.
+        let mut reader = BgzfReader::new(&compressed);
+        reader.seek(((starts[2] as u64) << 16) | 100).unwrap();
+        let mut buf = vec![0u8; BGZF_BLOCK_SIZE];
+        assert_eq!(reader.read(&mut buf).unwrap(), buf.len());
+        let start = 2 * BGZF_BLOCK_SIZE + 100;
+        assert_eq!(buf, &input[start..start + BGZF_BLOCK_SIZE]);
+        assert_eq!(reader.virtual_offset(), ((starts[3] as u64) << 16) | 100);
+
+        assert!(reader.seek(((starts[1] as u64) << 16) | 0xffff).is_err());
+    }
+
+    #[test]
+    fn test_decompress_truncated() {
+        let compressed =
+            GzipCompressor::compress_all(b"hello world", CompressionOptions::default()).unwrap();
+        let truncated = &compressed[..compressed.len() - 4];
+        let options = DecompressionOptions::default();
+        assert!(GzipDecompressor::decompress_all(truncated, options).is_err());
+    }
+
+    #[test]
+    fn test_decompression_limits() {
+        let input = vec![0u8; 1 << 20];
+        let compressed =
+            GzipCompressor::compress_all(&input, CompressionOptions::default()).unwrap();
+
+        let options = DecompressionOptions {
+            max_output_size: Some(100_000),
+            ..Default::default()
+        };
+        assert!(matches!(
+            GzipDecompressor::decompress_all(&compressed, options),
+            Err(GzipError::LimitExceeded(LimitExceeded::OutputSize(100_000)))
+        ));
+
+        let options = DecompressionOptions {
+            max_ratio: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            GzipDecompressor::decompress_all(&compressed, options),
+            Err(GzipError::LimitExceeded(LimitExceeded::Ratio(100)))
+        ));
+
+        let options = DecompressionOptions {
+            max_output_size: Some(1 << 20),
+            max_ratio: Some(2000),
+        };
+        assert_eq!(GzipDecompressor::decompress_all(&compressed, options).unwrap(), input);
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let compressed =
+            GzipCompressor::compress_all(b"hello world", CompressionOptions::default()).unwrap();
+        let kind = |input: &[u8]| {
+            GzipDecompressor::decompress_all(input, Default::default()).unwrap_err().kind()
+        };
+        assert_eq!(kind(b"not gzip data"), ErrorKind::CorruptInput);
+        assert_eq!(kind(&compressed[..compressed.len() - 4]), ErrorKind::UnexpectedEof);
+        let mut bad_crc = compressed.clone();
+        let len = bad_crc.len();
+        bad_crc[len - 8] ^= 0xff;
+        assert_eq!(kind(&bad_crc), ErrorKind::ChecksumMismatch);
+
+        let mut decompressor = GzipDecompressor::new(Default::default()).unwrap();
+        decompressor.decompress_chunk(&compressed, true).unwrap();
+        let err = decompressor.decompress_chunk(&compressed, true).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UsedAfterFinish);
+
+        let options = CompressionOptions {
+            window_log: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(GzipCompressor::new(options).err().unwrap().kind(), ErrorKind::InvalidOption);
+    }
+}
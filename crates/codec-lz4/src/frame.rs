@@ -0,0 +1,503 @@
+//! LZ4 frame encoder and push-based parser.
+//!
+//! Bytes are fed in as they arrive and every block is encoded or decoded as
+//! soon as it is complete, so memory stays bounded by one block (plus the
+//! 64 KiB window that linked blocks reference) no matter how large the frame is.
+
+use crate::{Lz4BlockSize, Lz4Error};
+use core_api::ErrorKind;
+use std::hash::Hasher;
+use twox_hash::XxHash32;
+
+const LZ4_MAGIC: u32 = 0x184D_2204;
+// Skippable frames use magic numbers 0x184D2A50..=0x184D2A5F.
+const SKIPPABLE_MAGIC_MASK: u32 = 0xFFFF_FFF0;
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+/// Linked blocks may reference up to 64 KiB of earlier output.
+const WINDOW_SIZE: usize = 64 * 1024;
+/// Set in a block size word when the block is stored uncompressed.
+const UNCOMPRESSED_FLAG: u32 = 0x8000_0000;
+
+fn read_u32(buf: &[u8]) -> u32 {
+    u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])
+}
+
+fn corrupt(msg: &str) -> Lz4Error {
+    Lz4Error::new(ErrorKind::CorruptInput, msg)
+}
+
+fn checksum_mismatch(msg: &str) -> Lz4Error {
+    Lz4Error::new(ErrorKind::ChecksumMismatch, msg)
+}
+
+/// Fields of an LZ4 frame descriptor (the frame header after the magic
+/// number).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lz4FrameDescriptor {
+    /// Largest uncompressed size of a block: 64 KiB, 256 KiB, 1 MiB or 4 MiB.
+    pub block_max_size: usize,
+    /// False when blocks may reference the 64 KiB of output before them.
+    pub independent_blocks: bool,
+    /// Each block is followed by an xxHash32 of its stored bytes.
+    pub block_checksums: bool,
+    /// The frame ends with an xxHash32 of the whole uncompressed content.
+    pub content_checksum: bool,
+    /// Uncompressed size of the frame, when the encoder recorded it.
+    pub content_size: Option<u64>,
+    /// ID of the dictionary the frame was compressed against.
+    pub dict_id: Option<u32>,
+}
+
+impl Default for Lz4FrameDescriptor {
+    /// 64 KiB independent blocks without checksums, as `lz4_flex` writes.
+    fn default() -> Self {
+        Self {
+            block_max_size: 64 * 1024,
+            independent_blocks: true,
+            block_checksums: false,
+            content_checksum: false,
+            content_size: None,
+            dict_id: None,
+        }
+    }
+}
+
+impl Lz4FrameDescriptor {
+    /// Encode the frame header (magic number included). Fails if
+    /// `block_max_size` is not one of the four sizes LZ4 frames can describe.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Lz4Error> {
+        let mut flg = 0b0100_0000;
+        if self.independent_blocks {
+            flg |= 0x20;
+        }
+        if self.block_checksums {
+            flg |= 0x10;
+        }
+        if self.content_size.is_some() {
+            flg |= 0x08;
+        }
+        if self.content_checksum {
+            flg |= 0x04;
+        }
+        if self.dict_id.is_some() {
+            flg |= 0x01;
+        }
+        let bd = match Lz4BlockSize::from_bytes(self.block_max_size)? {
+            Lz4BlockSize::Max64KB => 4,
+            Lz4BlockSize::Max256KB => 5,
+            Lz4BlockSize::Max1MB => 6,
+            Lz4BlockSize::Max4MB => 7,
+        } << 4;
+
+        let mut out = LZ4_MAGIC.to_le_bytes().to_vec();
+        out.extend_from_slice(&[flg, bd]);
+        if let Some(size) = self.content_size {
+            out.extend_from_slice(&size.to_le_bytes());
+        }
+        if let Some(id) = self.dict_id {
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+        out.push((XxHash32::oneshot(0, &out[4..]) >> 8) as u8);
+        Ok(out)
+    }
+
+    /// Parse the frame header at the start of `buf` (magic number included).
+    ///
+    /// Returns `None` until the whole header has arrived, otherwise the
+    /// descriptor and the header length.
+    pub fn parse(buf: &[u8]) -> Result<Option<(Self, usize)>, Lz4Error> {
+        if buf.len() >= 4 && read_u32(buf) != LZ4_MAGIC {
+            return Err(corrupt("Invalid LZ4 frame magic number"));
+        }
+        if buf.len() < 6 {
+            return Ok(None);
+        }
+        let flg = buf[4];
+        let bd = buf[5];
+        if flg >> 6 != 0b01 {
+            return Err(corrupt("Unsupported LZ4 frame version"));
+        }
+        if flg & 0x02 != 0 || bd & 0x8F != 0 {
+            return Err(corrupt("Reserved bits set in LZ4 frame descriptor"));
+        }
+        let has_content_size = flg & 0x08 != 0;
+        let has_dict_id = flg & 0x01 != 0;
+
+        let header_len =
+            6 + if has_content_size { 8 } else { 0 } + if has_dict_id { 4 } else { 0 } + 1;
+        if buf.len() < header_len {
+            return Ok(None);
+        }
+        // The header checksum covers the descriptor (FLG through DictID).
+        let checksum = (XxHash32::oneshot(0, &buf[4..header_len - 1]) >> 8) as u8;
+        if checksum != buf[header_len - 1] {
+            return Err(checksum_mismatch("LZ4 frame header checksum mismatch"));
+        }
+
+        let block_max_size = match (bd >> 4) & 0x07 {
+            4 => 64 * 1024,
+            5 => 256 * 1024,
+            6 => 1024 * 1024,
+            7 => 4 * 1024 * 1024,
+            _ => return Err(corrupt("Invalid LZ4 block maximum size")),
+        };
+        let mut offset = 6;
+        let content_size = if has_content_size {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&buf[offset..offset + 8]);
+            offset += 8;
+            Some(u64::from_le_bytes(bytes))
+        } else {
+            None
+        };
+        let dict_id = has_dict_id.then(|| read_u32(&buf[offset..]));
+
+        let descriptor = Self {
+            block_max_size,
+            independent_blocks: flg & 0x20 != 0,
+            block_checksums: flg & 0x10 != 0,
+            content_checksum: flg & 0x04 != 0,
+            content_size,
+            dict_id,
+        };
+        Ok(Some((descriptor, header_len)))
+    }
+}
+
+/// Incremental encoder for a single LZ4 frame.
+pub(crate) struct FrameEncoder {
+    descriptor: Lz4FrameDescriptor,
+    /// Compression level; `LZ4_HC_MIN_LEVEL` and above use the HC encoder.
+    level: u32,
+    /// The encoded frame header, until the first call to `write` sends it.
+    header: Vec<u8>,
+    /// Input of the block being filled.
+    block: Vec<u8>,
+    /// The last 64 KiB of the dictionary; empty without one.
+    dictionary: Vec<u8>,
+    /// Trailing input of the frame, seeded with the dictionary and
+    /// referenced by linked blocks.
+    window: Vec<u8>,
+    content_hash: XxHash32,
+    content_len: u64,
+}
+
+/// The part of a dictionary blocks can reach: its last 64 KiB.
+fn dictionary_window(dictionary: &[u8]) -> Vec<u8> {
+    dictionary[dictionary.len().saturating_sub(WINDOW_SIZE)..].to_vec()
+}
+
+impl FrameEncoder {
+    /// `dictionary` must match `descriptor.dict_id`, if set.
+    pub fn new(
+        descriptor: Lz4FrameDescriptor,
+        level: u32,
+        dictionary: &[u8],
+    ) -> Result<Self, Lz4Error> {
+        let dictionary = dictionary_window(dictionary);
+        Ok(Self {
+            header: descriptor.to_bytes()?,
+            descriptor,
+            level,
+            block: Vec::new(),
+            window: dictionary.clone(),
+            dictionary,
+            content_hash: XxHash32::with_seed(0),
+            content_len: 0,
+        })
+    }
+
+    /// Buffer `input`, appending every block it completes to `output`. The
+    /// header goes out with the first call.
+    pub fn write(&mut self, mut input: &[u8], output: &mut Vec<u8>) {
+        if !self.header.is_empty() {
+            output.append(&mut self.header);
+        }
+        loop {
+            let take = (self.descriptor.block_max_size - self.block.len()).min(input.len());
+            self.block.extend_from_slice(&input[..take]);
+            input = &input[take..];
+            if self.block.len() < self.descriptor.block_max_size {
+                break;
+            }
+            self.write_block(output);
+        }
+    }
+
+    /// Close the current block early, if it holds anything.
+    pub fn flush(&mut self, output: &mut Vec<u8>) {
+        if !self.block.is_empty() {
+            self.write_block(output);
+        }
+    }
+
+    /// Stop later blocks from referencing anything written so far.
+    pub fn reset_window(&mut self) {
+        self.window.clear();
+    }
+
+    /// Write the last block, the end mark and the content checksum.
+    pub fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), Lz4Error> {
+        self.write(&[], output);
+        self.flush(output);
+        if self.descriptor.content_size.is_some_and(|size| size != self.content_len) {
+            return Err(Lz4Error::new(
+                ErrorKind::InvalidOption,
+                "LZ4 content size does not match the input",
+            ));
+        }
+        output.extend_from_slice(&0u32.to_le_bytes());
+        if self.descriptor.content_checksum {
+            output.extend_from_slice(&self.content_hash.finish_32().to_le_bytes());
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, output: &mut Vec<u8>) {
+        let data = std::mem::take(&mut self.block);
+        // Independent blocks each start from the dictionary alone.
+        let prefix: &[u8] = if self.descriptor.independent_blocks {
+            &self.dictionary
+        } else {
+            &self.window
+        };
+        let compressed = if self.level >= crate::LZ4_HC_MIN_LEVEL {
+            crate::hc::compress(&data, prefix, self.level)
+        } else if prefix.is_empty() {
+            lz4_flex::block::compress(&data)
+        } else {
+            lz4_flex::block::compress_with_dict(&data, prefix)
+        };
+
+        let (word, payload) = if compressed.len() < data.len() {
+            (compressed.len() as u32, &compressed[..])
+        } else {
+            (data.len() as u32 | UNCOMPRESSED_FLAG, &data[..])
+        };
+        output.extend_from_slice(&word.to_le_bytes());
+        output.extend_from_slice(payload);
+        if self.descriptor.block_checksums {
+            output.extend_from_slice(&XxHash32::oneshot(0, payload).to_le_bytes());
+        }
+
+        if self.descriptor.content_checksum {
+            self.content_hash.write(&data);
+        }
+        self.content_len += data.len() as u64;
+        if !self.descriptor.independent_blocks {
+            self.window.extend_from_slice(&data);
+            if self.window.len() > WINDOW_SIZE {
+                self.window.drain(..self.window.len() - WINDOW_SIZE);
+            }
+        }
+
+        // Keep the allocation for the next block.
+        self.block = data;
+        self.block.clear();
+    }
+}
+
+enum State {
+    /// Expecting the magic number of the next frame.
+    Magic,
+    /// Discarding the rest of a skippable frame.
+    Skip(usize),
+    /// Expecting the next block (or the end mark) of the current frame.
+    Block(Lz4FrameDescriptor),
+    /// End mark seen; expecting the content checksum.
+    ContentChecksum(Lz4FrameDescriptor),
+}
+
+/// Incremental decoder for a sequence of LZ4 frames.
+pub(crate) struct FrameDecoder {
+    state: State,
+    /// Descriptor of the current (or last) frame.
+    descriptor: Option<Lz4FrameDescriptor>,
+    /// Output of the current block.
+    scratch: Vec<u8>,
+    /// The last 64 KiB of the dictionary; empty without one.
+    dictionary: Vec<u8>,
+    /// ID of the dictionary, checked against the ID frames carry.
+    dictionary_id: Option<u32>,
+    /// Trailing output of the current frame, seeded with the dictionary and
+    /// referenced by linked blocks.
+    window: Vec<u8>,
+    content_hash: XxHash32,
+    content_len: u64,
+}
+
+impl FrameDecoder {
+    pub fn new(dictionary: Option<&[u8]>) -> Self {
+        Self {
+            state: State::Magic,
+            descriptor: None,
+            scratch: Vec::new(),
+            dictionary: dictionary.map(dictionary_window).unwrap_or_default(),
+            dictionary_id: dictionary.map(crate::dictionary_id),
+            window: Vec::new(),
+            content_hash: XxHash32::with_seed(0),
+            content_len: 0,
+        }
+    }
+
+    pub fn descriptor(&self) -> Option<&Lz4FrameDescriptor> {
+        self.descriptor.as_ref()
+    }
+
+    /// True when no frame is partially decoded.
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, State::Magic)
+    }
+
+    /// Decode every complete unit (header, block, checksum) at the start of
+    /// `input`, passing each decoded block to `emit`. Returns the number of
+    /// bytes consumed; the caller keeps the rest and offers it again with
+    /// more input appended.
+    pub fn decode<F>(&mut self, input: &[u8], mut emit: F) -> Result<usize, Lz4Error>
+    where
+        F: FnMut(&[u8]) -> Result<(), Lz4Error>,
+    {
+        let mut pos = 0;
+        loop {
+            let available = &input[pos..];
+            match self.state {
+                State::Magic => {
+                    if available.len() < 4 {
+                        break;
+                    }
+                    let magic = read_u32(available);
+                    if magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC {
+                        if available.len() < 8 {
+                            break;
+                        }
+                        self.state = State::Skip(read_u32(&available[4..]) as usize);
+                        pos += 8;
+                        continue;
+                    }
+                    let Some((descriptor, header_len)) = Lz4FrameDescriptor::parse(available)?
+                    else {
+                        break;
+                    };
+                    match (descriptor.dict_id, self.dictionary_id) {
+                        (Some(id), None) => {
+                            return Err(Lz4Error::new(
+                                ErrorKind::InvalidOption,
+                                format!("LZ4 frame requires dictionary {}", id),
+                            ));
+                        }
+                        (Some(id), Some(ours)) if id != ours => {
+                            return Err(Lz4Error::new(
+                                ErrorKind::InvalidOption,
+                                "LZ4 dictionary ID mismatch",
+                            ));
+                        }
+                        _ => {}
+                    }
+                    self.descriptor = Some(descriptor);
+                    self.scratch.resize(descriptor.block_max_size, 0);
+                    self.window.clone_from(&self.dictionary);
+                    self.content_hash = XxHash32::with_seed(0);
+                    self.content_len = 0;
+                    self.state = State::Block(descriptor);
+                    pos += header_len;
+                }
+                State::Skip(remaining) => {
+                    let n = remaining.min(available.len());
+                    pos += n;
+                    if n < remaining {
+                        self.state = State::Skip(remaining - n);
+                        break;
+                    }
+                    self.state = State::Magic;
+                }
+                State::Block(descriptor) => {
+                    if available.len() < 4 {
+                        break;
+                    }
+                    let word = read_u32(available);
+                    if word == 0 {
+                        // End mark
+                        pos += 4;
+                        if descriptor.content_checksum {
+                            self.state = State::ContentChecksum(descriptor);
+                        } else {
+                            self.end_frame(&descriptor)?;
+                        }
+                        continue;
+                    }
+
+                    let len = (word & !UNCOMPRESSED_FLAG) as usize;
+                    if len > descriptor.block_max_size {
+                        return Err(corrupt("LZ4 block exceeds the frame's maximum block size"));
+                    }
+                    let checksum_len = if descriptor.block_checksums { 4 } else { 0 };
+                    if available.len() < 4 + len + checksum_len {
+                        break;
+                    }
+                    let data = &available[4..4 + len];
+                    if descriptor.block_checksums
+                        && XxHash32::oneshot(0, data) != read_u32(&available[4 + len..])
+                    {
+                        return Err(checksum_mismatch("LZ4 block checksum mismatch"));
+                    }
+
+                    let decoded: &[u8] = if word & UNCOMPRESSED_FLAG != 0 {
+                        data
+                    } else {
+                        let prefix = if descriptor.independent_blocks {
+                            &self.dictionary
+                        } else {
+                            &self.window
+                        };
+                        let n = if prefix.is_empty() {
+                            lz4_flex::block::decompress_into(data, &mut self.scratch)
+                        } else {
+                            lz4_flex::block::decompress_into_with_dict(
+                                data,
+                                &mut self.scratch,
+                                prefix,
+                            )
+                        }
+                        .map_err(|e| Lz4Error::new(ErrorKind::CorruptInput, e.to_string()))?;
+                        &self.scratch[..n]
+                    };
+
+                    if descriptor.content_checksum {
+                        self.content_hash.write(decoded);
+                    }
+                    self.content_len += decoded.len() as u64;
+                    if !descriptor.independent_blocks {
+                        self.window.extend_from_slice(decoded);
+                        if self.window.len() > WINDOW_SIZE {
+                            self.window.drain(..self.window.len() - WINDOW_SIZE);
+                        }
+                    }
+                    emit(decoded)?;
+                    pos += 4 + len + checksum_len;
+                }
+                State::ContentChecksum(descriptor) => {
+                    if available.len() < 4 {
+                        break;
+                    }
+                    if read_u32(available) != self.content_hash.finish_32() {
+                        return Err(checksum_mismatch("LZ4 content checksum mismatch"));
+                    }
+                    pos += 4;
+                    self.end_frame(&descriptor)?;
+                }
+            }
+        }
+        Ok(pos)
+    }
+
+    fn end_frame(&mut self, descriptor: &Lz4FrameDescriptor) -> Result<(), Lz4Error> {
+        if let Some(size) = descriptor.content_size {
+            if size != self.content_len {
+                return Err(corrupt("LZ4 frame content size mismatch"));
+            }
+        }
+        self.window.clear();
+        self.state = State::Magic;
+        Ok(())
+    }
+}
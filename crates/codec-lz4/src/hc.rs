@@ -0,0 +1,173 @@
+//! High-compression LZ4 block encoder.
+//!
+//! A hash-chain match finder with lazy matching, in the spirit of the
+//! reference `lz4hc`. It spends more time searching than the fast encoder
+//! but writes the same block format, so any LZ4 decoder reads its output.
+
+/// Matches shorter than this cannot be encoded.
+const MIN_MATCH: usize = 4;
+/// The last five bytes of a block are always literals.
+const LAST_LITERALS: usize = 5;
+/// The last match must start at least twelve bytes before the end of a block.
+const MF_LIMIT: usize = 12;
+/// Largest offset a match can encode.
+const MAX_DISTANCE: usize = 65535;
+/// Largest prefix a block can reference.
+const WINDOW_SIZE: usize = 64 * 1024;
+const HASH_LOG: u32 = 15;
+
+fn hash(buf: &[u8], pos: usize) -> usize {
+    let v = u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]]);
+    (v.wrapping_mul(2_654_435_761) >> (32 - HASH_LOG)) as usize
+}
+
+/// Candidates examined per position at `level`, doubling with each level.
+fn search_attempts(level: u32) -> usize {
+    1 << (level.clamp(crate::LZ4_HC_MIN_LEVEL, crate::LZ4_MAX_LEVEL) + 1)
+}
+
+/// Hash chains over `buf`: `head` holds the latest position for each hash,
+/// `chain` the distance from a position back to the previous one with the
+/// same hash (0 ends the chain).
+struct MatchFinder<'a> {
+    buf: &'a [u8],
+    head: Vec<usize>,
+    chain: Vec<u16>,
+    /// Next position still to be inserted.
+    next: usize,
+    attempts: usize,
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(buf: &'a [u8], level: u32) -> Self {
+        Self {
+            buf,
+            head: vec![usize::MAX; 1 << HASH_LOG],
+            chain: vec![0; WINDOW_SIZE],
+            next: 0,
+            attempts: search_attempts(level),
+        }
+    }
+
+    /// Insert every position before `target` that has four bytes to hash.
+    fn insert_until(&mut self, target: usize) {
+        let target = target.min(self.buf.len().saturating_sub(MIN_MATCH - 1));
+        while self.next < target {
+            let pos = self.next;
+            let h = hash(self.buf, pos);
+            let prev = self.head[h];
+            let delta = if prev == usize::MAX { 0 } else { pos - prev };
+            self.chain[pos % WINDOW_SIZE] = if delta > MAX_DISTANCE { 0 } else { delta as u16 };
+            self.head[h] = pos;
+            self.next += 1;
+        }
+    }
+
+    /// Longest match for `pos` that ends by `limit`, as (length, position).
+    fn find(&mut self, pos: usize, limit: usize) -> Option<(usize, usize)> {
+        self.insert_until(pos);
+        let buf = self.buf;
+        let max_len = limit - pos;
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[hash(buf, pos)];
+        let mut attempts = self.attempts;
+        while candidate != usize::MAX && attempts > 0 && pos - candidate <= MAX_DISTANCE {
+            attempts -= 1;
+            let best_len = best.map_or(MIN_MATCH - 1, |(len, _)| len);
+            if best_len < max_len && buf[candidate + best_len] == buf[pos + best_len] {
+                let len = buf[candidate..]
+                    .iter()
+                    .zip(&buf[pos..limit])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best = Some((len, candidate));
+                    if len == max_len {
+                        break;
+                    }
+                }
+            }
+            let delta = self.chain[candidate % WINDOW_SIZE] as usize;
+            if delta == 0 || delta > candidate {
+                break;
+            }
+            candidate -= delta;
+        }
+        best
+    }
+}
+
+/// Append a length continuation: runs of 255 ended by a smaller byte.
+fn write_length(mut len: usize, out: &mut Vec<u8>) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+/// Append one sequence: `literals`, then a match unless `matched` is `None`.
+fn write_sequence(literals: &[u8], matched: Option<(usize, usize)>, out: &mut Vec<u8>) {
+    let lit_len = literals.len();
+    let match_code = matched.map_or(0, |(len, _)| len - MIN_MATCH);
+    out.push(((lit_len.min(15) as u8) << 4) | match_code.min(15) as u8);
+    if lit_len >= 15 {
+        write_length(lit_len - 15, out);
+    }
+    out.extend_from_slice(literals);
+    if let Some((_, offset)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_code >= 15 {
+            write_length(match_code - 15, out);
+        }
+    }
+}
+
+/// Compress `input` as one LZ4 block at `level`, appending it to `out`.
+///
+/// Matches may reach back into `prefix` (the data that precedes the block:
+/// earlier output for linked blocks, or a dictionary).
+fn compress_into(input: &[u8], prefix: &[u8], level: u32, out: &mut Vec<u8>) {
+    let prefix = &prefix[prefix.len().saturating_sub(WINDOW_SIZE)..];
+    let mut buf = Vec::with_capacity(prefix.len() + input.len());
+    buf.extend_from_slice(prefix);
+    buf.extend_from_slice(input);
+
+    let start = prefix.len();
+    let end = buf.len();
+    let mut anchor = start;
+    if input.len() > MF_LIMIT {
+        let mut finder = MatchFinder::new(&buf, level);
+        let match_limit = end - LAST_LITERALS;
+        let last_start = end - MF_LIMIT;
+        let mut pos = start;
+        while pos <= last_start {
+            let Some(mut matched) = finder.find(pos, match_limit) else {
+                pos += 1;
+                continue;
+            };
+            // Lazy matching: a longer match one byte on is worth a literal.
+            while pos < last_start {
+                match finder.find(pos + 1, match_limit) {
+                    Some(next) if next.0 > matched.0 => {
+                        pos += 1;
+                        matched = next;
+                    }
+                    _ => break,
+                }
+            }
+            let (len, candidate) = matched;
+            write_sequence(&buf[anchor..pos], Some((len, pos - candidate)), out);
+            pos += len;
+            anchor = pos;
+        }
+    }
+    write_sequence(&buf[anchor..end], None, out);
+}
+
+/// Compress `input` as one LZ4 block at `level`.
+pub(crate) fn compress(input: &[u8], prefix: &[u8], level: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(lz4_flex::block::get_maximum_output_size(input.len()));
+    compress_into(input, prefix, level, &mut out);
+    out
+}
@@ -1,74 +1,317 @@
-use core_api::{CompressionOptions, Compressor, Flush};
+use core_api::{
+    CodecError, CompressionOptions, Compressor, DecompressionLimits, DecompressionOptions,
+    Decompressor, ErrorKind, Flush, HasErrorKind, LimitExceeded,
+};
+
+mod frame;
+mod hc;
+
+pub use frame::Lz4FrameDescriptor;
+use twox_hash::XxHash32;
+
+/// Lowest level that uses the high-compression (HC) match finder; levels
+/// below it use the fast encoder.
+pub const LZ4_HC_MIN_LEVEL: u32 = 3;
+/// Highest useful level; higher levels behave like this one.
+pub const LZ4_MAX_LEVEL: u32 = 12;
 
 /// Error type for LZ4 compression.
 #[derive(Debug)]
 pub enum Lz4Error {
-    Other(String),
+    /// Decompressed output ran past a `DecompressionOptions` limit.
+    LimitExceeded(LimitExceeded),
+    /// Any other failure, with its kind.
+    Codec(CodecError),
 }
 
 impl std::fmt::Display for Lz4Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Lz4Error::Other(msg) => write!(f, "{}", msg),
+            Lz4Error::LimitExceeded(e) => write!(f, "{}", e),
+            Lz4Error::Codec(e) => write!(f, "{}", e),
         }
     }
 }
 
 impl std::error::Error for Lz4Error {}
 
+impl Lz4Error {
+    /// A `Codec` error of `kind`.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Lz4Error::Codec(CodecError::new(kind, message))
+    }
+}
+
+impl HasErrorKind for Lz4Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Lz4Error::LimitExceeded(_) => ErrorKind::LimitExceeded,
+            Lz4Error::Codec(e) => e.kind(),
+        }
+    }
+}
+
+impl From<LimitExceeded> for Lz4Error {
+    fn from(e: LimitExceeded) -> Self {
+        Lz4Error::LimitExceeded(e)
+    }
+}
+
+/// Largest uncompressed size of a block in an LZ4 frame. Bigger blocks
+/// compress a little better; decoders need a buffer of this size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lz4BlockSize {
+    #[default]
+    Max64KB,
+    Max256KB,
+    Max1MB,
+    Max4MB,
+}
+
+impl Lz4BlockSize {
+    /// The block size of `bytes` bytes, if LZ4 frames can describe it.
+    pub fn from_bytes(bytes: usize) -> Result<Self, Lz4Error> {
+        match bytes {
+            0x1_0000 => Ok(Lz4BlockSize::Max64KB),
+            0x4_0000 => Ok(Lz4BlockSize::Max256KB),
+            0x10_0000 => Ok(Lz4BlockSize::Max1MB),
+            0x40_0000 => Ok(Lz4BlockSize::Max4MB),
+            _ => Err(Lz4Error::new(
+                ErrorKind::InvalidOption,
+                format!("Unsupported LZ4 block size {}", bytes),
+            )),
+        }
+    }
+
+    pub fn bytes(self) -> usize {
+        match self {
+            Lz4BlockSize::Max64KB => 64 * 1024,
+            Lz4BlockSize::Max256KB => 256 * 1024,
+            Lz4BlockSize::Max1MB => 1024 * 1024,
+            Lz4BlockSize::Max4MB => 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// LZ4 frame settings for the compressor. The defaults match `lz4_flex` and
+/// the reference library: 64 KiB independent blocks, no checksums.
+#[derive(Debug, Clone, Default)]
+pub struct Lz4FrameOptions {
+    pub block_size: Lz4BlockSize,
+    /// Let each block reference the 64 KiB of input before it. Improves the
+    /// ratio of small blocks, but blocks can then only be decoded in order.
+    pub linked_blocks: bool,
+    /// Follow each block with an xxHash32 of its stored bytes.
+    pub block_checksums: bool,
+    /// End the frame with an xxHash32 of the whole input.
+    pub content_checksum: bool,
+    /// Total input size, recorded in the header. Finishing after a different
+    /// amount of input is an error.
+    pub content_size: Option<u64>,
+    /// Data the frame may reference as if it preceded the input; only its
+    /// last 64 KiB are used. The header carries its `dictionary_id`, and the
+    /// decompressor needs the same bytes.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+/// Dictionary ID written to frames compressed against `dictionary`: the
+/// xxHash32 of its bytes.
+pub fn dictionary_id(dictionary: &[u8]) -> u32 {
+    XxHash32::oneshot(0, dictionary)
+}
+
+/// Compress `input` as a raw LZ4 block, without a frame. `dictionary` is
+/// data the block may reference as if it preceded the input; the
+/// decompressor needs the same bytes.
+pub fn compress_block(input: &[u8], dictionary: Option<&[u8]>) -> Vec<u8> {
+    match dictionary {
+        Some(dictionary) => lz4_flex::block::compress_with_dict(input, dictionary),
+        None => lz4_flex::block::compress(input),
+    }
+}
+
+/// Decompress a raw LZ4 block of at most `max_size` bytes written by
+/// `compress_block`.
+pub fn decompress_block(
+    input: &[u8],
+    max_size: usize,
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>, Lz4Error> {
+    match dictionary {
+        Some(dictionary) => lz4_flex::block::decompress_with_dict(input, max_size, dictionary),
+        None => lz4_flex::block::decompress(input, max_size),
+    }
+    .map_err(|e| Lz4Error::new(ErrorKind::CorruptInput, e.to_string()))
+}
+
 /// LZ4 compressor.
-/// Note: LZ4 is a single-speed algorithm - no compression levels.
-/// We accumulate input since LZ4 doesn't have a native streaming API.
+/// Levels below `LZ4_HC_MIN_LEVEL` (including the default) use the fast
+/// encoder; levels from there up to `LZ4_MAX_LEVEL` use the HC match finder,
+/// searching harder at each level. Both write standard LZ4 frames.
+/// Input is fed to a long-lived frame encoder, which emits each block as soon
+/// as it fills up, so memory stays bounded by the frame block size.
 pub struct Lz4Compressor {
-    buffer: Vec<u8>,
+    encoder: frame::FrameEncoder,
     finished: bool,
 }
 
+impl Lz4Compressor {
+    /// Create a compressor writing frames with the given settings.
+    pub fn with_options(
+        options: CompressionOptions,
+        frame: Lz4FrameOptions,
+    ) -> Result<Self, Lz4Error> {
+        let descriptor = Lz4FrameDescriptor {
+            block_max_size: frame.block_size.bytes(),
+            independent_blocks: !frame.linked_blocks,
+            block_checksums: frame.block_checksums,
+            content_checksum: frame.content_checksum,
+            content_size: frame.content_size,
+            dict_id: frame.dictionary.as_deref().map(dictionary_id),
+        };
+        let level = options.level.unwrap_or(0);
+        let dictionary = frame.dictionary.as_deref().unwrap_or_default();
+        Ok(Self {
+            encoder: frame::FrameEncoder::new(descriptor, level, dictionary)?,
+            finished: false,
+        })
+    }
+}
+
 impl Compressor for Lz4Compressor {
     type Error = Lz4Error;
 
-    fn new(_options: CompressionOptions) -> Result<Self, Self::Error> {
+    fn new(options: CompressionOptions) -> Result<Self, Self::Error> {
+        Self::with_options(options, Lz4FrameOptions::default())
+    }
+
+    fn compress_chunk(
+        &mut self,
+        input: &[u8],
+        flush: Flush,
+    ) -> Result<Vec<u8>, Self::Error> {
+        if self.finished {
+            return Err(Lz4Error::new(ErrorKind::UsedAfterFinish, "Cannot compress after finish"));
+        }
+
+        // Completed blocks go out right away; a partially filled block stays
+        // in the encoder until it fills up, is flushed, or we finish.
+        let mut output = Vec::new();
+        self.encoder.write(input, &mut output);
+        match flush {
+            Flush::None => {}
+            // Close the current block early; Full also keeps linked blocks
+            // from referencing anything before it.
+            Flush::Sync => self.encoder.flush(&mut output),
+            Flush::Full => {
+                self.encoder.flush(&mut output);
+                self.encoder.reset_window();
+            }
+            Flush::Finish => {
+                self.finished = true;
+                self.encoder.finish(&mut output)?;
+            }
+        }
+        Ok(output)
+    }
+
+    fn compress_all(
+        input: &[u8],
+        options: CompressionOptions,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let mut compressor = Self::new(options)?;
+        compressor.compress_chunk(input, Flush::Finish)
+    }
+}
+
+/// LZ4-specific decompressor settings.
+#[derive(Debug, Clone, Default)]
+pub struct Lz4DecompressOptions {
+    /// Dictionary the frames were compressed against. Frames carrying a
+    /// dictionary ID are rejected without it, or when its `dictionary_id`
+    /// differs.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+/// LZ4 frame decompressor.
+/// Each block is decoded as soon as all of its bytes have arrived; only the
+/// unfinished tail of the input is buffered between calls.
+pub struct Lz4Decompressor {
+    decoder: frame::FrameDecoder,
+    buffer: Vec<u8>,
+    limits: DecompressionLimits,
+    finished: bool,
+}
+
+impl Lz4Decompressor {
+    pub fn with_options(
+        options: DecompressionOptions,
+        lz4: Lz4DecompressOptions,
+    ) -> Result<Self, Lz4Error> {
         Ok(Self {
+            decoder: frame::FrameDecoder::new(lz4.dictionary.as_deref()),
             buffer: Vec::new(),
+            limits: DecompressionLimits::new(options),
             finished: false,
         })
     }
 
-    fn compress_chunk(
+    /// Descriptor of the frame being decoded (or the last one), once its
+    /// header has been received.
+    pub fn frame_descriptor(&self) -> Option<&Lz4FrameDescriptor> {
+        self.decoder.descriptor()
+    }
+}
+
+impl Decompressor for Lz4Decompressor {
+    type Error = Lz4Error;
+
+    fn new(options: DecompressionOptions) -> Result<Self, Self::Error> {
+        Self::with_options(options, Lz4DecompressOptions::default())
+    }
+
+    fn decompress_chunk(
         &mut self,
         input: &[u8],
-        flush: Flush,
+        finish: bool,
     ) -> Result<Vec<u8>, Self::Error> {
         if self.finished {
-            return Err(Lz4Error::Other("Cannot compress after finish".to_string()));
+            return Err(Lz4Error::new(ErrorKind::UsedAfterFinish, "Cannot decompress after finish"));
         }
 
-        self.buffer.extend_from_slice(input);
+        self.limits.add_input(input.len());
+        let mut output = Vec::new();
+        let limits = &mut self.limits;
+        // Limits are checked block by block, so a bomb stops within one block.
+        let emit = |block: &[u8]| {
+            limits.add_output(block.len())?;
+            output.extend_from_slice(block);
+            Ok(())
+        };
+        if self.buffer.is_empty() {
+            let used = self.decoder.decode(input, emit)?;
+            self.buffer.extend_from_slice(&input[used..]);
+        } else {
+            self.buffer.extend_from_slice(input);
+            let used = self.decoder.decode(&self.buffer, emit)?;
+            self.buffer.drain(..used);
+        }
 
-        if matches!(flush, Flush::Finish) {
+        if finish {
             self.finished = true;
-            // Use frame::FrameEncoder to produce standard LZ4 frames
-            use std::io::Write;
-            let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
-            encoder.write_all(&self.buffer).map_err(|e| Lz4Error::Other(e.to_string()))?;
-            let compressed = encoder.finish().map_err(|e| Lz4Error::Other(e.to_string()))?;
-            Ok(compressed)
-        } else {
-            // LZ4 doesn't support partial compression, so return empty until finish
-            Ok(Vec::new())
+            if !self.decoder.is_idle() || !self.buffer.is_empty() {
+                return Err(Lz4Error::new(ErrorKind::UnexpectedEof, "Truncated LZ4 frame"));
+            }
         }
+        Ok(output)
     }
 
-    fn compress_all(
+    fn decompress_all(
         input: &[u8],
-        _options: CompressionOptions,
+        options: DecompressionOptions,
     ) -> Result<Vec<u8>, Self::Error> {
-        // Use frame::FrameEncoder to produce standard LZ4 frames compatible with other libraries
-        use std::io::Write;
-        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
-        encoder.write_all(input).map_err(|e| Lz4Error::Other(e.to_string()))?;
-        let compressed = encoder.finish().map_err(|e| Lz4Error::Other(e.to_string()))?;
-        Ok(compressed)
+        let mut decoder = Self::new(options)?;
+        decoder.decompress_chunk(input, true)
     }
 }
 
@@ -91,5 +334,324 @@ mod tests {
         let result = compressor.compress_chunk(b"world", Flush::Finish).unwrap();
         assert!(!result.is_empty());
     }
-}
 
+    #[test]
+    fn test_streaming_emits_blocks_before_finish() {
+        // Larger than the default 64 KB block size, so at least one block is complete.
+        let input: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut compressor = Lz4Compressor::new(CompressionOptions::default()).unwrap();
+        let mut compressed = compressor.compress_chunk(&input, Flush::None).unwrap();
+        assert!(!compressed.is_empty());
+        compressed.extend(compressor.compress_chunk(&[], Flush::Finish).unwrap());
+        let output = Lz4Decompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), input);
+    }
+
+    #[test]
+    fn test_sync_flush_closes_block() {
+        let mut compressor = Lz4Compressor::new(CompressionOptions::default()).unwrap();
+        // Only the frame header is out before the block is closed
+        let mut compressed = compressor.compress_chunk(b"hello ", Flush::None).unwrap();
+        assert_eq!(compressed.len(), 7);
+        let block = compressor.compress_chunk(b"world", Flush::Sync).unwrap();
+        assert!(!block.is_empty());
+        compressed.extend(block);
+        compressed.extend(compressor.compress_chunk(b"!", Flush::Finish).unwrap());
+        let output = Lz4Decompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), b"hello world!");
+    }
+
+    #[test]
+    fn test_decompress_roundtrip() {
+        let input = b"hello hello hello world";
+        let compressed = Lz4Compressor::compress_all(input, CompressionOptions::default()).unwrap();
+        let output = Lz4Decompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), input);
+
+        let mut decompressor = Lz4Decompressor::new(DecompressionOptions::default()).unwrap();
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+        let mut output = decompressor.decompress_chunk(head, false).unwrap();
+        output.extend(decompressor.decompress_chunk(tail, true).unwrap());
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_incremental_decompression() {
+        let input: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        // The frame uses 64 KB blocks, so it has several.
+        let mut compressor = Lz4Compressor::new(CompressionOptions::default()).unwrap();
+        let mut compressed = Vec::new();
+        for chunk in input.chunks(10_000) {
+            compressed.extend(compressor.compress_chunk(chunk, Flush::None).unwrap());
+        }
+        compressed.extend(compressor.compress_chunk(&[], Flush::Finish).unwrap());
+
+        // Output arrives block by block, well before the end of the frame.
+        let mut decompressor = Lz4Decompressor::new(DecompressionOptions::default()).unwrap();
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+        let mut output = decompressor.decompress_chunk(head, false).unwrap();
+        assert!(!output.is_empty());
+        output.extend(decompressor.decompress_chunk(tail, true).unwrap());
+        assert_eq!(output, input);
+
+        let mut decompressor = Lz4Decompressor::new(DecompressionOptions::default()).unwrap();
+        let mut output = Vec::new();
+        for chunk in compressed.chunks(1000) {
+            output.extend(decompressor.decompress_chunk(chunk, false).unwrap());
+        }
+        output.extend(decompressor.decompress_chunk(&[], true).unwrap());
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_linked_blocks_and_checksums() {
+        use lz4_flex::frame::{BlockMode, BlockSize, FrameEncoder, FrameInfo};
+        use std::io::Write;
+
+        let input = b"linked blocks reach back into earlier output. ".repeat(5000);
+        let info = FrameInfo::new()
+            .block_size(BlockSize::Max64KB)
+            .block_mode(BlockMode::Linked)
+            .block_checksums(true)
+            .content_checksum(true)
+            .content_size(Some(input.len() as u64));
+        let mut encoder = FrameEncoder::with_frame_info(info, Vec::new());
+        encoder.write_all(&input).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decompressor = Lz4Decompressor::new(DecompressionOptions::default()).unwrap();
+        let mut output = Vec::new();
+        for chunk in compressed.chunks(333) {
+            output.extend(decompressor.decompress_chunk(chunk, false).unwrap());
+        }
+        output.extend(decompressor.decompress_chunk(&[], true).unwrap());
+        assert_eq!(output, input);
+
+        // A flipped byte in the last block trips its checksum.
+        let mut corrupted = compressed.clone();
+        let at = corrupted.len() - 12;
+        corrupted[at] ^= 0xff;
+        let options = DecompressionOptions::default();
+        assert!(Lz4Decompressor::decompress_all(&corrupted, options).is_err());
+    }
+
+    #[test]
+    fn test_hc_levels() {
+        use std::io::Read;
+
+        // Text with long-range repeats the fast encoder's single probe misses.
+        let words = ["alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta"];
+        let input: Vec<u8> = (0..20_000u32)
+            .flat_map(|i| {
+                let word = words[((i * 7) ^ (i >> 3)) as usize % words.len()];
+                format!("{} {} ", word, i % 97).into_bytes()
+            })
+            .collect();
+        let fast = Lz4Compressor::compress_all(&input, CompressionOptions::default()).unwrap();
+
+        for level in [LZ4_HC_MIN_LEVEL, 9, LZ4_MAX_LEVEL] {
+            let options = CompressionOptions {
+                level: Some(level),
+                ..Default::default()
+            };
+            let compressed = Lz4Compressor::compress_all(&input, options).unwrap();
+            assert!(compressed.len() < fast.len());
+            let output = Lz4Decompressor::decompress_all(&compressed, DecompressionOptions::default());
+            assert_eq!(output.unwrap(), input);
+
+            // Any LZ4 frame reader accepts HC output.
+            let mut output = Vec::new();
+            lz4_flex::frame::FrameDecoder::new(&compressed[..])
+                .read_to_end(&mut output)
+                .unwrap();
+            assert_eq!(output, input);
+        }
+
+        // Streaming at an HC level, with sync flushes between small blocks.
+        let options = CompressionOptions {
+            level: Some(9),
+            ..Default::default()
+        };
+        let mut compressor = Lz4Compressor::new(options).unwrap();
+        let mut compressed = Vec::new();
+        for chunk in input.chunks(5000) {
+            compressed.extend(compressor.compress_chunk(chunk, Flush::Sync).unwrap());
+        }
+        compressed.extend(compressor.compress_chunk(&[], Flush::Finish).unwrap());
+        let output = Lz4Decompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), input);
+    }
+
+    #[test]
+    fn test_frame_options() {
+        use std::io::Read;
+
+        let input = b"frame options reach the header. ".repeat(20_000);
+        for linked_blocks in [false, true] {
+            let frame = Lz4FrameOptions {
+                block_size: Lz4BlockSize::Max256KB,
+                linked_blocks,
+                block_checksums: true,
+                content_checksum: true,
+                content_size: Some(input.len() as u64),
+                dictionary: None,
+            };
+            let mut compressor =
+                Lz4Compressor::with_options(CompressionOptions::default(), frame).unwrap();
+            let mut compressed = Vec::new();
+            for chunk in input.chunks(100_000) {
+                compressed.extend(compressor.compress_chunk(chunk, Flush::Full).unwrap());
+            }
+            compressed.extend(compressor.compress_chunk(&[], Flush::Finish).unwrap());
+
+            let expected = Lz4FrameDescriptor {
+                block_max_size: 256 * 1024,
+                independent_blocks: !linked_blocks,
+                block_checksums: true,
+                content_checksum: true,
+                content_size: Some(input.len() as u64),
+                dict_id: None,
+            };
+            let (descriptor, _) = Lz4FrameDescriptor::parse(&compressed).unwrap().unwrap();
+            assert_eq!(descriptor, expected);
+
+            let mut decompressor = Lz4Decompressor::new(DecompressionOptions::default()).unwrap();
+            assert!(decompressor.frame_descriptor().is_none());
+            let mut output = decompressor.decompress_chunk(&compressed[..100], false).unwrap();
+            assert_eq!(decompressor.frame_descriptor(), Some(&expected));
+            output.extend(decompressor.decompress_chunk(&compressed[100..], true).unwrap());
+            assert_eq!(output, input);
+
+            let mut output = Vec::new();
+            lz4_flex::frame::FrameDecoder::new(&compressed[..])
+                .read_to_end(&mut output)
+                .unwrap();
+            assert_eq!(output, input);
+        }
+
+        // The recorded content size must match the input.
+        let frame = Lz4FrameOptions {
+            content_size: Some(10),
+            ..Default::default()
+        };
+        let mut compressor =
+            Lz4Compressor::with_options(CompressionOptions::default(), frame).unwrap();
+        let err = compressor.compress_chunk(b"hello", Flush::Finish).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidOption);
+
+        // Only the four frame block sizes can be encoded.
+        assert_eq!(Lz4BlockSize::from_bytes(0x10_0000).unwrap(), Lz4BlockSize::Max1MB);
+        let err = Lz4BlockSize::from_bytes(0x2_0000).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidOption);
+        let descriptor = Lz4FrameDescriptor {
+            block_max_size: 1000,
+            ..Default::default()
+        };
+        assert_eq!(descriptor.to_bytes().unwrap_err().kind(), ErrorKind::InvalidOption);
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let dictionary = b"{\"event\":\"page_view\",\"user\":\"\",\"path\":\"/\",\"ms\":}".repeat(4);
+        let message = b"{\"event\":\"page_view\",\"user\":\"u-1234\",\"path\":\"/docs\",\"ms\":87}";
+
+        // Block mode: the dictionary supplies history the message lacks.
+        let plain = compress_block(message, None);
+        let compressed = compress_block(message, Some(&dictionary));
+        assert!(compressed.len() < plain.len());
+        let output = decompress_block(&compressed, message.len(), Some(&dictionary)).unwrap();
+        assert_eq!(output, message);
+
+        // Frame mode, fast and HC, with independent and linked blocks.
+        for (level, linked_blocks) in [(0, false), (0, true), (9, false), (9, true)] {
+            let options = CompressionOptions {
+                level: Some(level),
+                ..Default::default()
+            };
+            let frame = Lz4FrameOptions {
+                linked_blocks,
+                dictionary: Some(dictionary.clone()),
+                ..Default::default()
+            };
+            let mut compressor = Lz4Compressor::with_options(options, frame).unwrap();
+            let mut compressed = compressor.compress_chunk(message, Flush::Sync).unwrap();
+            compressed.extend(compressor.compress_chunk(message, Flush::Finish).unwrap());
+            let (descriptor, _) = Lz4FrameDescriptor::parse(&compressed).unwrap().unwrap();
+            assert_eq!(descriptor.dict_id, Some(dictionary_id(&dictionary)));
+
+            let lz4 = Lz4DecompressOptions {
+                dictionary: Some(dictionary.clone()),
+            };
+            let mut decompressor =
+                Lz4Decompressor::with_options(DecompressionOptions::default(), lz4).unwrap();
+            let output = decompressor.decompress_chunk(&compressed, true).unwrap();
+            assert_eq!(output, message.repeat(2));
+
+            // Without the dictionary, or with another one, the frame is rejected.
+            let options = DecompressionOptions::default();
+            assert!(Lz4Decompressor::decompress_all(&compressed, options).is_err());
+            let lz4 = Lz4DecompressOptions {
+                dictionary: Some(b"another dictionary".to_vec()),
+            };
+            let mut decompressor =
+                Lz4Decompressor::with_options(DecompressionOptions::default(), lz4).unwrap();
+            assert!(decompressor.decompress_chunk(&compressed, true).is_err());
+        }
+    }
+
+    #[test]
+    fn test_decompress_truncated() {
+        let compressed =
+            Lz4Compressor::compress_all(b"hello world", CompressionOptions::default()).unwrap();
+        let truncated = &compressed[..compressed.len() - 2];
+        let options = DecompressionOptions::default();
+        assert!(Lz4Decompressor::decompress_all(truncated, options).is_err());
+    }
+
+    #[test]
+    fn test_decompression_limits() {
+        let input = vec![0u8; 1 << 20];
+        let compressed = Lz4Compressor::compress_all(&input, CompressionOptions::default()).unwrap();
+
+        let options = DecompressionOptions {
+            max_output_size: Some(100_000),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Lz4Decompressor::decompress_all(&compressed, options),
+            Err(Lz4Error::LimitExceeded(LimitExceeded::OutputSize(100_000)))
+        ));
+
+        let options = DecompressionOptions {
+            max_ratio: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Lz4Decompressor::decompress_all(&compressed, options),
+            Err(Lz4Error::LimitExceeded(LimitExceeded::Ratio(100)))
+        ));
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let frame = Lz4FrameOptions {
+            content_checksum: true,
+            ..Default::default()
+        };
+        let mut compressor =
+            Lz4Compressor::with_options(CompressionOptions::default(), frame).unwrap();
+        let compressed = compressor.compress_chunk(b"hello world", Flush::Finish).unwrap();
+        let kind = |input: &[u8]| {
+            Lz4Decompressor::decompress_all(input, Default::default()).unwrap_err().kind()
+        };
+        assert_eq!(kind(b"not lz4 data"), ErrorKind::CorruptInput);
+        assert_eq!(kind(&compressed[..compressed.len() - 2]), ErrorKind::UnexpectedEof);
+        let mut bad_checksum = compressed.clone();
+        let len = bad_checksum.len();
+        bad_checksum[len - 1] ^= 0xff;
+        assert_eq!(kind(&bad_checksum), ErrorKind::ChecksumMismatch);
+
+        let err = compressor.compress_chunk(b"hello", Flush::Finish).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UsedAfterFinish);
+    }
+}
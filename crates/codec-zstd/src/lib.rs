@@ -0,0 +1,610 @@
+use core_api::{
+    CodecError, CompressionOptions, Compressor, DecompressionLimits, DecompressionOptions,
+    Decompressor, ErrorKind, Flush, HasErrorKind, LimitExceeded,
+};
+use ruzstd::decoding::FrameDecoder;
+use ruzstd::encoding::CompressionLevel;
+
+/// Error type for zstd compression.
+#[derive(Debug)]
+pub enum ZstdError {
+    Io(std::io::Error),
+    /// `window_log` outside 10..=17: frames hold at most 128 KiB of input, so
+    /// no bigger window would ever be used.
+    InvalidWindowLog(u32),
+    /// Level above 22, the highest zstd defines.
+    InvalidLevel(u32),
+    /// Decompressed output ran past a `DecompressionOptions` limit.
+    LimitExceeded(LimitExceeded),
+    /// Any other failure, with its kind.
+    Codec(CodecError),
+}
+
+impl std::fmt::Display for ZstdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZstdError::Io(e) => write!(f, "IO error: {}", e),
+            ZstdError::InvalidWindowLog(w) => {
+                write!(f, "Invalid zstd window log {} (expected 10..=17)", w)
+            }
+            ZstdError::InvalidLevel(l) => write!(f, "Invalid zstd level {} (expected 0..=22)", l),
+            ZstdError::LimitExceeded(e) => write!(f, "{}", e),
+            ZstdError::Codec(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ZstdError {}
+
+impl ZstdError {
+    /// A `Codec` error of `kind`.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        ZstdError::Codec(CodecError::new(kind, message))
+    }
+}
+
+impl HasErrorKind for ZstdError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ZstdError::Io(e) => ErrorKind::from_io(e),
+            ZstdError::InvalidWindowLog(_) | ZstdError::InvalidLevel(_) => ErrorKind::InvalidOption,
+            ZstdError::LimitExceeded(_) => ErrorKind::LimitExceeded,
+            ZstdError::Codec(e) => e.kind(),
+        }
+    }
+}
+
+impl From<std::io::Error> for ZstdError {
+    fn from(e: std::io::Error) -> Self {
+        ZstdError::Io(e)
+    }
+}
+
+impl From<LimitExceeded> for ZstdError {
+    fn from(e: LimitExceeded) -> Self {
+        ZstdError::LimitExceeded(e)
+    }
+}
+
+const ZSTD_MAGIC: u32 = 0xFD2F_B528;
+// Skippable frames use magic numbers 0x184D2A50..=0x184D2A5F.
+const SKIPPABLE_MAGIC_MASK: u32 = 0xFFFF_FFF0;
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+
+// ZSTD_WINDOWLOG_MIN.
+const ZSTD_MIN_WINDOW_LOG: u32 = 10;
+/// Frames hold at most `2^window_log` bytes of input and the streaming
+/// compressor emits nothing until a frame fills, so the window is capped at
+/// 128 KiB to bound what it buffers.
+const ZSTD_MAX_WINDOW_LOG: u32 = 17;
+const ZSTD_MAX_LEVEL: u32 = 22;
+
+/// Map the common options onto a ruzstd level and the maximum number of input
+/// bytes per frame.
+///
+/// ruzstd only implements the "fastest" match finder (roughly zstd level 1),
+/// so level 0 stores the data uncompressed and every other level uses it.
+/// Frames hold `2^window_log` bytes of input (128 KiB by default), which
+/// bounds both how much the streaming compressor buffers and the history a
+/// decoder needs.
+fn frame_params(options: &CompressionOptions) -> Result<(CompressionLevel, usize), ZstdError> {
+    let level = match options.level.unwrap_or(3) {
+        0 => CompressionLevel::Uncompressed,
+        level if level <= ZSTD_MAX_LEVEL => CompressionLevel::Fastest,
+        level => return Err(ZstdError::InvalidLevel(level)),
+    };
+    let window_log = options.window_log.unwrap_or(ZSTD_MAX_WINDOW_LOG);
+    if !(ZSTD_MIN_WINDOW_LOG..=ZSTD_MAX_WINDOW_LOG).contains(&window_log) {
+        return Err(ZstdError::InvalidWindowLog(window_log));
+    }
+    Ok((level, 1 << window_log))
+}
+
+/// Zstandard compressor.
+///
+/// Input is buffered until a full frame's worth is available, then emitted as
+/// a complete zstd frame. Concatenated frames decode to the concatenated
+/// content, so the output is a regular zstd stream.
+///
+/// Options: `level` 0 stores the data uncompressed, and every level from 1
+/// to 22 compresses identically, with the fast match finder (roughly
+/// `zstd -1`); the level number is otherwise ignored. `window_log` is the
+/// frame size, 10..=17, defaulting to 17 (128 KiB).
+pub struct ZstdCompressor {
+    level: CompressionLevel,
+    frame_size: usize,
+    buffer: Vec<u8>,
+    frames_written: usize,
+    finished: bool,
+}
+
+impl ZstdCompressor {
+    /// Compress `data` as one complete frame and append it to `output`.
+    fn write_frame(&mut self, data: &[u8], output: &mut Vec<u8>) {
+        ruzstd::encoding::compress(data, &mut *output, self.level);
+        self.frames_written += 1;
+    }
+}
+
+impl Compressor for ZstdCompressor {
+    type Error = ZstdError;
+
+    fn new(options: CompressionOptions) -> Result<Self, Self::Error> {
+        let (level, frame_size) = frame_params(&options)?;
+        Ok(Self {
+            level,
+            frame_size,
+            buffer: Vec::new(),
+            frames_written: 0,
+            finished: false,
+        })
+    }
+
+    fn compress_chunk(
+        &mut self,
+        input: &[u8],
+        flush: Flush,
+    ) -> Result<Vec<u8>, Self::Error> {
+        if self.finished {
+            return Err(ZstdError::new(ErrorKind::UsedAfterFinish, "Cannot compress after finish"));
+        }
+
+        let mut output = Vec::new();
+        let mut input = input;
+
+        // Emit every full frame without copying it through the buffer first.
+        while self.buffer.len() + input.len() >= self.frame_size {
+            let (head, rest) = input.split_at(self.frame_size - self.buffer.len());
+            if self.buffer.is_empty() {
+                self.write_frame(head, &mut output);
+            } else {
+                let mut frame = std::mem::take(&mut self.buffer);
+                frame.extend_from_slice(head);
+                self.write_frame(&frame, &mut output);
+                frame.clear();
+                self.buffer = frame;
+            }
+            input = rest;
+        }
+        self.buffer.extend_from_slice(input);
+
+        let close_frame = match flush {
+            Flush::None => false,
+            // Frames are independent, so Sync and Full both close the current one.
+            Flush::Sync | Flush::Full => !self.buffer.is_empty(),
+            Flush::Finish => {
+                self.finished = true;
+                // An empty stream still needs one (empty) frame.
+                !self.buffer.is_empty() || self.frames_written == 0
+            }
+        };
+        if close_frame {
+            let frame = std::mem::take(&mut self.buffer);
+            self.write_frame(&frame, &mut output);
+        }
+        Ok(output)
+    }
+
+    fn compress_all(
+        input: &[u8],
+        options: CompressionOptions,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let mut compressor = Self::new(options)?;
+        compressor.compress_chunk(input, Flush::Finish)
+    }
+}
+
+/// Length of the frame header starting at `buf`, or `None` if not enough bytes
+/// have arrived to tell. Skippable frames report their full length.
+fn frame_header_len(buf: &[u8]) -> Result<Option<usize>, ZstdError> {
+    if buf.len() < 4 {
+        return Ok(None);
+    }
+    let magic = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    if magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC {
+        if buf.len() < 8 {
+            return Ok(None);
+        }
+        let size = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+        return Ok(Some(8 + size));
+    }
+    if magic != ZSTD_MAGIC {
+        return Err(ZstdError::new(ErrorKind::CorruptInput, "Invalid zstd frame magic number"));
+    }
+    if buf.len() < 5 {
+        return Ok(None);
+    }
+    let descriptor = buf[4];
+    let single_segment = descriptor & 0x20 != 0;
+    let window_descriptor = if single_segment { 0 } else { 1 };
+    let dict_id = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+    let content_size = match descriptor >> 6 {
+        0 => usize::from(single_segment),
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    Ok(Some(5 + window_descriptor + dict_id + content_size))
+}
+
+/// Window size declared by the complete frame header `header`: the content
+/// size for a single-segment frame, otherwise from the window descriptor.
+fn window_size(header: &[u8]) -> u64 {
+    let descriptor = header[4];
+    if descriptor & 0x20 == 0 {
+        let exponent = u64::from(header[5] >> 3);
+        let base = 1u64 << (10 + exponent);
+        return base + base / 8 * u64::from(header[5] & 7);
+    }
+    let dict_id = [0, 1, 2, 4][(descriptor & 0x03) as usize];
+    let field = &header[5 + dict_id..];
+    match descriptor >> 6 {
+        0 => u64::from(field[0]),
+        1 => u64::from(u16::from_le_bytes([field[0], field[1]])) + 256,
+        2 => u64::from(u32::from_le_bytes([field[0], field[1], field[2], field[3]])),
+        _ => u64::from_le_bytes(field[..8].try_into().unwrap()),
+    }
+}
+
+/// Length of the block starting at `buf`, header included, or `None` if its
+/// header hasn't arrived yet. The last block of a frame with a checksum
+/// includes the checksum.
+fn block_len(buf: &[u8], has_checksum: bool) -> Option<usize> {
+    let header = u32::from_le_bytes([*buf.first()?, *buf.get(1)?, *buf.get(2)?, 0]);
+    let last_block = header & 1 != 0;
+    let content_len = match (header >> 1) & 3 {
+        // An RLE block stores its one byte, whatever size it decodes to.
+        1 => 1,
+        _ => (header >> 3) as usize,
+    };
+    let checksum_len = if last_block && has_checksum { 4 } else { 0 };
+    Some(3 + content_len + checksum_len)
+}
+
+/// Zstandard decompressor.
+///
+/// Compressed input is buffered only until the next complete block is
+/// available; each block is decoded on its own as soon as it arrives, and
+/// limits are checked after every block. The decoder holds back the frame's
+/// window of output until the frame ends, so a frame whose window would take
+/// the stream past a limit is rejected up front, and otherwise a limit trips
+/// at most one window plus one block (128 KiB) of decoded data late.
+pub struct ZstdDecompressor {
+    decoder: FrameDecoder,
+    buffer: Vec<u8>,
+    limits: DecompressionLimits,
+    in_frame: bool,
+    has_checksum: bool,
+    finished: bool,
+}
+
+impl ZstdDecompressor {
+    /// Decode everything the buffered input allows, appending it to `output`.
+    fn decode_available(&mut self, output: &mut Vec<u8>) -> Result<(), ZstdError> {
+        let mut pos = 0;
+        loop {
+            let available = &self.buffer[pos..];
+            if !self.in_frame {
+                let header_len = match frame_header_len(available)? {
+                    Some(len) if len <= available.len() => len,
+                    _ => break,
+                };
+                if available[0] & 0xF0 == 0x50 {
+                    // Skippable frame: nothing to decode.
+                    pos += header_len;
+                    continue;
+                }
+                // The decoder holds back up to a window of output before any
+                // of it is counted, so a window the limits can't cover is
+                // refused before decoding starts.
+                self.limits.check_output(window_size(&available[..header_len]))?;
+                self.has_checksum = available[4] & 0x04 != 0;
+                let mut source = &available[..header_len];
+                self.decoder
+                    .init(&mut source)
+                    .map_err(|e| ZstdError::new(ErrorKind::CorruptInput, e.to_string()))?;
+                pos += header_len;
+                self.in_frame = true;
+                continue;
+            }
+
+            // Given more, the decoder would decode every complete block
+            // before the limits get a look at the output.
+            let block_len = match block_len(available, self.has_checksum) {
+                Some(len) if len <= available.len() => len,
+                _ => break,
+            };
+            let (read, _) = self
+                .decoder
+                .decode_from_to(&available[..block_len], &mut [])
+                .map_err(|e| ZstdError::new(ErrorKind::CorruptInput, e.to_string()))?;
+            pos += read;
+            if let Some(bytes) = self.decoder.collect() {
+                self.limits.add_output(bytes.len())?;
+                output.extend_from_slice(&bytes);
+            }
+
+            if self.decoder.is_finished() {
+                if let (Some(expected), Some(actual)) = (
+                    self.decoder.get_checksum_from_data(),
+                    self.decoder.get_calculated_checksum(),
+                ) {
+                    if expected != actual {
+                        return Err(ZstdError::new(
+                            ErrorKind::ChecksumMismatch,
+                            "zstd checksum mismatch",
+                        ));
+                    }
+                }
+                self.in_frame = false;
+            } else if read == 0 {
+                break;
+            }
+        }
+        self.buffer.drain(..pos);
+        Ok(())
+    }
+}
+
+impl Decompressor for ZstdDecompressor {
+    type Error = ZstdError;
+
+    fn new(options: DecompressionOptions) -> Result<Self, Self::Error> {
+        Ok(Self {
+            decoder: FrameDecoder::new(),
+            buffer: Vec::new(),
+            limits: DecompressionLimits::new(options),
+            in_frame: false,
+            has_checksum: false,
+            finished: false,
+        })
+    }
+
+    fn decompress_chunk(
+        &mut self,
+        input: &[u8],
+        finish: bool,
+    ) -> Result<Vec<u8>, Self::Error> {
+        if self.finished {
+            return Err(ZstdError::new(
+                ErrorKind::UsedAfterFinish,
+                "Cannot decompress after finish",
+            ));
+        }
+
+        self.limits.add_input(input.len());
+        self.buffer.extend_from_slice(input);
+        let mut output = Vec::new();
+        self.decode_available(&mut output)?;
+
+        if finish {
+            self.finished = true;
+            if self.in_frame || !self.buffer.is_empty() {
+                return Err(ZstdError::new(ErrorKind::UnexpectedEof, "Truncated zstd stream"));
+            }
+        }
+        Ok(output)
+    }
+
+    fn decompress_all(
+        input: &[u8],
+        options: DecompressionOptions,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let mut decoder = Self::new(options)?;
+        decoder.decompress_chunk(input, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(len: usize) -> Vec<u8> {
+        b"zstd streams are made of frames and blocks. "
+            .iter()
+            .cycle()
+            .take(len)
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_compress_all() {
+        let input = sample(10_000);
+        let compressed =
+            ZstdCompressor::compress_all(&input, CompressionOptions::default()).unwrap();
+        assert!(compressed.len() < input.len());
+        let output = ZstdDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert_eq!(output.unwrap(), input);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let compressed = ZstdCompressor::compress_all(b"", CompressionOptions::default()).unwrap();
+        assert!(!compressed.is_empty());
+        let output = ZstdDecompressor::decompress_all(&compressed, DecompressionOptions::default());
+        assert!(output.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_streaming() {
+        // Small frames so the stream spans several of them.
+        let options = CompressionOptions {
+            window_log: Some(12),
+            ..Default::default()
+        };
+        let input = sample(20_000);
+        let mut compressor = ZstdCompressor::new(options).unwrap();
+        let mut compressed = Vec::new();
+        for chunk in input.chunks(3000) {
+            compressed.extend(compressor.compress_chunk(chunk, Flush::None).unwrap());
+        }
+        assert!(!compressed.is_empty());
+        compressed.extend(compressor.compress_chunk(b"", Flush::Finish).unwrap());
+
+        let mut decompressor = ZstdDecompressor::new(DecompressionOptions::default()).unwrap();
+        let mut output = Vec::new();
+        for chunk in compressed.chunks(7) {
+            output.extend(decompressor.decompress_chunk(chunk, false).unwrap());
+        }
+        output.extend(decompressor.decompress_chunk(b"", true).unwrap());
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_sync_flush() {
+        let mut compressor = ZstdCompressor::new(CompressionOptions::default()).unwrap();
+        let mut decompressor = ZstdDecompressor::new(DecompressionOptions::default()).unwrap();
+        let out = compressor.compress_chunk(b"hello ", Flush::Sync).unwrap();
+        assert_eq!(decompressor.decompress_chunk(&out, false).unwrap(), b"hello ");
+        let out = compressor.compress_chunk(b"world", Flush::Finish).unwrap();
+        assert_eq!(decompressor.decompress_chunk(&out, true).unwrap(), b"world");
+    }
+
+    #[test]
+    fn test_truncated() {
+        let compressed =
+            ZstdCompressor::compress_all(&sample(1000), CompressionOptions::default()).unwrap();
+        let truncated = &compressed[..compressed.len() - 6];
+        let options = DecompressionOptions::default();
+        assert!(ZstdDecompressor::decompress_all(truncated, options).is_err());
+    }
+
+    #[test]
+    fn test_invalid_window_log() {
+        for window_log in [9, 18, 30] {
+            let options = CompressionOptions {
+                window_log: Some(window_log),
+                ..Default::default()
+            };
+            assert!(matches!(
+                ZstdCompressor::new(options),
+                Err(ZstdError::InvalidWindowLog(w)) if w == window_log
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_level() {
+        let options = CompressionOptions {
+            level: Some(23),
+            ..Default::default()
+        };
+        assert!(matches!(ZstdCompressor::new(options), Err(ZstdError::InvalidLevel(23))));
+    }
+
+    #[test]
+    fn test_frame_size_capped() {
+        // With the default (and largest) window, a frame closes once 128 KiB
+        // are buffered.
+        let mut compressor = ZstdCompressor::new(CompressionOptions::default()).unwrap();
+        let input = sample((1 << ZSTD_MAX_WINDOW_LOG) + 1);
+        let mut compressed = compressor.compress_chunk(&input, Flush::None).unwrap();
+        assert!(!compressed.is_empty());
+        compressed.extend(compressor.compress_chunk(&[], Flush::Finish).unwrap());
+        let output = ZstdDecompressor::decompress_all(&compressed, Default::default());
+        assert_eq!(output.unwrap(), input);
+    }
+
+    #[test]
+    fn test_limits_checked_per_block() {
+        // 2,000 RLE blocks of 128 KiB each: 8 KB of input for 250 MiB of output.
+        let mut frame = ZSTD_MAGIC.to_le_bytes().to_vec();
+        // No checksum or content size, and a 1 MiB window.
+        frame.extend_from_slice(&[0x00, 0x50]);
+        for i in 0..2000 {
+            let header = (128 * 1024) << 3 | 1 << 1 | u32::from(i == 1999);
+            frame.extend_from_slice(&header.to_le_bytes()[..3]);
+            frame.push(b'z');
+        }
+
+        let options = DecompressionOptions {
+            max_output_size: Some(1 << 20),
+            ..Default::default()
+        };
+        let mut decompressor = ZstdDecompressor::new(options).unwrap();
+        assert!(matches!(
+            decompressor.decompress_chunk(&frame, true),
+            Err(ZstdError::LimitExceeded(LimitExceeded::OutputSize(_)))
+        ));
+        // The limit trips within a window (8 blocks) and a block of 1 MiB.
+        assert!(decompressor.decoder.blocks_decoded() <= 17);
+    }
+
+    #[test]
+    fn test_large_window_rejected() {
+        // A 64 MiB window holding a single one-byte raw block.
+        let mut frame = ZSTD_MAGIC.to_le_bytes().to_vec();
+        frame.extend_from_slice(&[0x00, 0x80]);
+        frame.extend_from_slice(&(1u32 << 3 | 1).to_le_bytes()[..3]);
+        frame.push(b'x');
+        assert_eq!(window_size(&frame[..6]), 64 << 20);
+        let output = ZstdDecompressor::decompress_all(&frame, Default::default());
+        assert_eq!(output.unwrap(), b"x");
+
+        let options = DecompressionOptions {
+            max_output_size: Some(1 << 20),
+            ..Default::default()
+        };
+        let mut decompressor = ZstdDecompressor::new(options).unwrap();
+        assert!(matches!(
+            decompressor.decompress_chunk(&frame[..6], false),
+            Err(ZstdError::LimitExceeded(LimitExceeded::OutputSize(_)))
+        ));
+
+        // A single-segment frame's window is its content size.
+        let mut frame = ZSTD_MAGIC.to_le_bytes().to_vec();
+        frame.extend_from_slice(&[0xA0]);
+        frame.extend_from_slice(&(64u32 << 20).to_le_bytes());
+        assert_eq!(window_size(&frame), 64 << 20);
+        let options = DecompressionOptions {
+            max_output_size: Some(1 << 20),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ZstdDecompressor::decompress_all(&frame, options),
+            Err(ZstdError::LimitExceeded(LimitExceeded::OutputSize(_)))
+        ));
+    }
+
+    #[test]
+    fn test_decompression_limits() {
+        let input = vec![0u8; 1 << 20];
+        let compressed =
+            ZstdCompressor::compress_all(&input, CompressionOptions::default()).unwrap();
+
+        let options = DecompressionOptions {
+            max_output_size: Some(100_000),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ZstdDecompressor::decompress_all(&compressed, options),
+            Err(ZstdError::LimitExceeded(LimitExceeded::OutputSize(100_000)))
+        ));
+
+        let options = DecompressionOptions {
+            max_ratio: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            ZstdDecompressor::decompress_all(&compressed, options),
+            Err(ZstdError::LimitExceeded(LimitExceeded::Ratio(100)))
+        ));
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let compressed =
+            ZstdCompressor::compress_all(&sample(1000), CompressionOptions::default()).unwrap();
+        let kind = |input: &[u8]| {
+            ZstdDecompressor::decompress_all(input, Default::default()).unwrap_err().kind()
+        };
+        assert_eq!(kind(b"not zstd data"), ErrorKind::CorruptInput);
+        assert_eq!(kind(&compressed[..compressed.len() - 6]), ErrorKind::UnexpectedEof);
+
+        let mut decompressor = ZstdDecompressor::new(Default::default()).unwrap();
+        decompressor.decompress_chunk(&compressed, true).unwrap();
+        let err = decompressor.decompress_chunk(&compressed, true).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UsedAfterFinish);
+    }
+}
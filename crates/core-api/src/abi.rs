@@ -0,0 +1,121 @@
+//! Error codes of the wasm bindings' C ABI.
+//!
+//! Exports that write into a caller's buffer return the byte count on
+//! success and a negative value otherwise: either an `ErrorCode`, or the
+//! output size needed when the buffer was too small (see `needed_size`). A
+//! needed size is never reported as less than 9 bytes, so it is always below
+//! `ErrorCode::LOWEST` and buffers of any size stay valid.
+//! Exports that return a handle return 0 on failure. Either way, the code and
+//! a description of the failure are kept for `last_error_code` and
+//! `last_error_message`.
+
+use std::sync::Mutex;
+
+use crate::ErrorKind;
+
+/// Why an export failed. The values are part of the ABI and never change.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// A failure no other code describes.
+    Failed = -1,
+    /// Decompression stopped at a configured output size or ratio limit.
+    LimitExceeded = -2,
+    /// A handle that is stale (destroyed or finished) or of the wrong kind.
+    InvalidHandle = -3,
+    /// The input is not valid compressed data.
+    CorruptData = -4,
+    /// The data decoded, but a checksum over it did not match.
+    ChecksumMismatch = -5,
+    /// The input ended in the middle of a stream.
+    TruncatedInput = -6,
+    /// Input was passed to a stream after it was finished.
+    AlreadyFinished = -7,
+    /// An output buffer could not be allocated.
+    OutOfMemory = -8,
+}
+
+impl ErrorCode {
+    /// The most negative code; anything below it is a needed size.
+    pub const LOWEST: isize = ErrorCode::OutOfMemory as isize;
+
+    pub fn code(self) -> isize {
+        self as isize
+    }
+}
+
+/// The code an error of `kind` is reported with. Kinds with no code of their
+/// own are `Failed`.
+impl From<ErrorKind> for ErrorCode {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::CorruptInput => ErrorCode::CorruptData,
+            ErrorKind::ChecksumMismatch => ErrorCode::ChecksumMismatch,
+            ErrorKind::UnexpectedEof => ErrorCode::TruncatedInput,
+            ErrorKind::UsedAfterFinish => ErrorCode::AlreadyFinished,
+            ErrorKind::LimitExceeded => ErrorCode::LimitExceeded,
+            ErrorKind::InvalidOption | ErrorKind::Io => ErrorCode::Failed,
+        }
+    }
+}
+
+struct LastError {
+    code: ErrorCode,
+    message: String,
+}
+
+static LAST_ERROR: Mutex<LastError> = Mutex::new(LastError {
+    code: ErrorCode::Failed,
+    message: String::new(),
+});
+
+/// Record a failure for `last_error_code` and `last_error_message`, and
+/// return its code for the export to pass on.
+pub fn fail(code: ErrorCode, message: impl std::fmt::Display) -> isize {
+    let mut last = LAST_ERROR.lock().unwrap();
+    last.code = code;
+    last.message = message.to_string();
+    code.code()
+}
+
+/// Code of the most recent failure.
+pub fn last_error_code() -> ErrorCode {
+    LAST_ERROR.lock().unwrap().code
+}
+
+/// Description of the most recent failure, empty if nothing has failed yet.
+pub fn last_error_message() -> String {
+    LAST_ERROR.lock().unwrap().message.clone()
+}
+
+/// The return value for output of `len` bytes that doesn't fit the caller's
+/// buffer. Sizes of 8 bytes or less are rounded up to 9, which would read as
+/// an `ErrorCode` otherwise; asking for a slightly bigger buffer is harmless.
+pub fn needed_size(len: usize) -> isize {
+    -(len.max(ErrorCode::LOWEST.unsigned_abs() + 1) as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::HandleError;
+
+    #[test]
+    fn test_fail_records_last_error() {
+        let code = fail(ErrorCode::InvalidHandle, HandleError::Stale);
+        assert_eq!(code, -3);
+        assert_eq!(last_error_code(), ErrorCode::InvalidHandle);
+        assert_eq!(last_error_message(), "Invalid or stale handle");
+
+        let code = ErrorCode::from(ErrorKind::UnexpectedEof);
+        assert_eq!(fail(code, "Truncated stream"), ErrorCode::TruncatedInput.code());
+        assert_eq!(last_error_message(), "Truncated stream");
+    }
+
+    #[test]
+    fn test_needed_size() {
+        assert_eq!(needed_size(3), -9);
+        assert_eq!(needed_size(1000), -1000);
+        assert!(needed_size(0) < ErrorCode::LOWEST);
+    }
+}
@@ -0,0 +1,271 @@
+//! Handle registry for the wasm bindings.
+//!
+//! JS refers to Rust-side objects (streaming compressors, decompressors,
+//! dictionaries) by `u32` handles. A binding keeps all of them in one
+//! registry, so a handle is checked for both liveness and kind: the low bits
+//! index a slot, the high bits hold the slot's generation, which changes each
+//! time the slot is freed. A handle kept after `remove` (or passed to the
+//! wrong kind of export) is rejected rather than reaching another object.
+//! Handle 0 is never issued, so exports can keep returning it for failure.
+//!
+//! Freed slots are reused oldest first, and a slot whose generation runs out
+//! is retired instead of starting over, so no handle is ever issued twice.
+//!
+//! Bindings keep their registry behind a `Mutex` rather than allocating from
+//! an atomic counter: a slot, its generation and the free list change
+//! together, which a single atomic can't cover. Exports run on one thread in
+//! wasm, so the lock is never contended.
+
+use std::collections::VecDeque;
+
+/// Bits of a handle that index the slot; the rest hold its generation.
+const INDEX_BITS: u32 = 16;
+const INDEX_MASK: u32 = (1 << INDEX_BITS) - 1;
+/// A slot is retired once its generation reaches this. Generations start at
+/// 1, so a handle is never 0.
+const MAX_GENERATION: u32 = u32::MAX >> INDEX_BITS;
+
+/// Why a handle was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleError {
+    /// Never issued, already removed, or from a slot that has been reused.
+    Stale,
+    /// Live, but refers to a different kind of object.
+    WrongType,
+}
+
+impl std::fmt::Display for HandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandleError::Stale => write!(f, "Invalid or stale handle"),
+            HandleError::WrongType => write!(f, "Handle refers to a different kind of object"),
+        }
+    }
+}
+
+impl std::error::Error for HandleError {}
+
+/// One kind of object stored in a registry of entries `E`. Implemented by
+/// `handle_entries!` for each variant of a binding's entry enum.
+pub trait HandleKind<E>: Sized {
+    fn into_entry(self) -> E;
+    fn from_entry(entry: &E) -> Option<&Self>;
+    fn from_entry_mut(entry: &mut E) -> Option<&mut Self>;
+    fn from_entry_owned(entry: E) -> Option<Self>;
+}
+
+/// Declare the entry enum of a handle registry, one variant per kind of
+/// object, with a `HandleKind` impl for each payload type.
+///
+/// ```ignore
+/// handle_entries! {
+///     enum Entry {
+///         Compressor(CompressorState),
+///         Decompressor(DecompressorState),
+///     }
+/// }
+/// static HANDLES: Mutex<HandleRegistry<Entry>> = Mutex::new(HandleRegistry::new());
+/// ```
+#[macro_export]
+macro_rules! handle_entries {
+    (enum $entry:ident { $($variant:ident($kind:ty)),+ $(,)? }) => {
+        enum $entry {
+            $($variant($kind)),+
+        }
+
+        $(
+            impl $crate::handles::HandleKind<$entry> for $kind {
+                fn into_entry(self) -> $entry {
+                    $entry::$variant(self)
+                }
+
+                #[allow(unreachable_patterns)]
+                fn from_entry(entry: &$entry) -> Option<&Self> {
+                    match entry {
+                        $entry::$variant(value) => Some(value),
+                        _ => None,
+                    }
+                }
+
+                #[allow(unreachable_patterns)]
+                fn from_entry_mut(entry: &mut $entry) -> Option<&mut Self> {
+                    match entry {
+                        $entry::$variant(value) => Some(value),
+                        _ => None,
+                    }
+                }
+
+                #[allow(unreachable_patterns)]
+                fn from_entry_owned(entry: $entry) -> Option<Self> {
+                    match entry {
+                        $entry::$variant(value) => Some(value),
+                        _ => None,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+struct Slot<E> {
+    generation: u32,
+    entry: Option<E>,
+}
+
+/// Slot map from generation-checked handles to entries.
+pub struct HandleRegistry<E> {
+    slots: Vec<Slot<E>>,
+    /// Indexes of empty slots, reused before the table grows, oldest first so
+    /// generations advance evenly.
+    free: VecDeque<u32>,
+}
+
+impl<E> Default for HandleRegistry<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> HandleRegistry<E> {
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: VecDeque::new(),
+        }
+    }
+
+    /// Store `value` and return its handle, or `None` when every slot is
+    /// taken or retired.
+    pub fn insert<K: HandleKind<E>>(&mut self, value: K) -> Option<u32> {
+        let index = match self.free.pop_front() {
+            Some(index) => index,
+            None if self.slots.len() <= INDEX_MASK as usize => {
+                self.slots.push(Slot {
+                    generation: 1,
+                    entry: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+            None => return None,
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.entry = Some(value.into_entry());
+        Some((slot.generation << INDEX_BITS) | index)
+    }
+
+    /// The live entry `handle` refers to.
+    fn entry(&self, handle: u32) -> Result<&E, HandleError> {
+        let slot = self.slots.get((handle & INDEX_MASK) as usize);
+        match slot {
+            Some(slot) if slot.generation == handle >> INDEX_BITS => {
+                slot.entry.as_ref().ok_or(HandleError::Stale)
+            }
+            _ => Err(HandleError::Stale),
+        }
+    }
+
+    pub fn get<K: HandleKind<E>>(&self, handle: u32) -> Result<&K, HandleError> {
+        K::from_entry(self.entry(handle)?).ok_or(HandleError::WrongType)
+    }
+
+    pub fn get_mut<K: HandleKind<E>>(&mut self, handle: u32) -> Result<&mut K, HandleError> {
+        self.entry(handle)?;
+        let entry = self.slots[(handle & INDEX_MASK) as usize].entry.as_mut();
+        entry.and_then(K::from_entry_mut).ok_or(HandleError::WrongType)
+    }
+
+    /// Take the object out of the registry. The handle (and any copy of it)
+    /// is invalid from then on.
+    pub fn remove<K: HandleKind<E>>(&mut self, handle: u32) -> Result<K, HandleError> {
+        self.get::<K>(handle)?;
+        let index = handle & INDEX_MASK;
+        let slot = &mut self.slots[index as usize];
+        let entry = slot.entry.take().ok_or(HandleError::Stale)?;
+        if slot.generation < MAX_GENERATION {
+            slot.generation += 1;
+            self.free.push_back(index);
+        }
+        K::from_entry_owned(entry).ok_or(HandleError::WrongType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Compressor(u32);
+    struct Decompressor;
+
+    handle_entries! {
+        enum Entry {
+            Compressor(Compressor),
+            Decompressor(Decompressor),
+        }
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut registry = HandleRegistry::<Entry>::new();
+        let a = registry.insert(Compressor(1)).unwrap();
+        let b = registry.insert(Compressor(2)).unwrap();
+        assert!(a != 0 && b != 0 && a != b);
+        assert_eq!(registry.get::<Compressor>(b).unwrap().0, 2);
+        registry.get_mut::<Compressor>(a).unwrap().0 = 3;
+        assert_eq!(registry.remove::<Compressor>(a).unwrap().0, 3);
+        assert_eq!(registry.get::<Compressor>(b).unwrap().0, 2);
+        assert_eq!(registry.get::<Compressor>(0).err(), Some(HandleError::Stale));
+    }
+
+    #[test]
+    fn test_stale_and_wrong_type() {
+        let mut registry = HandleRegistry::<Entry>::new();
+        let old = registry.insert(Compressor(1)).unwrap();
+        registry.remove::<Compressor>(old).unwrap();
+
+        // The slot is reused under a new generation; the old handle stays dead.
+        let new = registry.insert(Decompressor).unwrap();
+        assert_eq!(old & INDEX_MASK, new & INDEX_MASK);
+        assert_ne!(old, new);
+        assert_eq!(registry.get::<Compressor>(old).err(), Some(HandleError::Stale));
+        assert_eq!(registry.remove::<Decompressor>(old).err(), Some(HandleError::Stale));
+
+        // A live handle of another kind is neither returned nor removed.
+        assert_eq!(registry.get::<Compressor>(new).err(), Some(HandleError::WrongType));
+        assert_eq!(registry.remove::<Compressor>(new).err(), Some(HandleError::WrongType));
+        assert!(registry.remove::<Decompressor>(new).is_ok());
+    }
+
+    #[test]
+    fn test_free_slots_reused_oldest_first() {
+        let mut registry = HandleRegistry::<Entry>::new();
+        let a = registry.insert(Compressor(1)).unwrap();
+        let b = registry.insert(Compressor(2)).unwrap();
+        registry.remove::<Compressor>(a).unwrap();
+        registry.remove::<Compressor>(b).unwrap();
+        let c = registry.insert(Compressor(3)).unwrap();
+        assert_eq!(c & INDEX_MASK, a & INDEX_MASK);
+    }
+
+    #[test]
+    fn test_slot_retired_instead_of_wrapping() {
+        // Cycle one slot through every generation it has.
+        let mut registry = HandleRegistry::<Entry>::new();
+        let first = registry.insert(Decompressor).unwrap();
+        let mut handle = first;
+        for _ in 1..MAX_GENERATION {
+            registry.remove::<Decompressor>(handle).unwrap();
+            handle = registry.insert(Decompressor).unwrap();
+            assert_eq!(handle & INDEX_MASK, first & INDEX_MASK);
+        }
+        assert_eq!(handle >> INDEX_BITS, MAX_GENERATION);
+
+        // Once it is used up, the slot is never handed out again, so the
+        // first handle can't come back to life.
+        registry.remove::<Decompressor>(handle).unwrap();
+        let next = registry.insert(Decompressor).unwrap();
+        assert_ne!(next & INDEX_MASK, first & INDEX_MASK);
+        assert_eq!(next >> INDEX_BITS, 1);
+        assert_eq!(registry.get::<Decompressor>(first).err(), Some(HandleError::Stale));
+        assert_eq!(registry.get::<Decompressor>(handle).err(), Some(HandleError::Stale));
+    }
+}
@@ -1,10 +1,20 @@
-/// Core traits and types for compression codecs.
+//! Core traits and types for compression codecs.
+
+pub mod abi;
+pub mod handles;
 
 /// Flush mode for streaming compression.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Flush {
     /// Continue compressing, don't flush output yet.
     None,
+    /// Flush all pending output to a byte boundary, so a receiver can decode
+    /// everything sent so far, and keep the stream open.
+    Sync,
+    /// Like `Sync`, but also reset the compression history so a receiver can
+    /// resynchronize from this point. Codecs without resettable history treat
+    /// this as `Sync`.
+    Full,
     /// Finish the stream and flush all remaining output.
     Finish,
 }
@@ -34,10 +44,185 @@ impl Default for CompressionOptions {
     }
 }
 
+/// Limits applied while decompressing untrusted input.
+#[derive(Debug, Clone, Default)]
+pub struct DecompressionOptions {
+    /// Maximum number of decompressed bytes for the whole stream.
+    pub max_output_size: Option<u64>,
+    /// Maximum ratio of decompressed to compressed bytes.
+    pub max_ratio: Option<u32>,
+}
+
+/// The decompression limit that a stream ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// Output grew past `max_output_size` bytes.
+    OutputSize(u64),
+    /// Output grew past `max_ratio` times the compressed input.
+    Ratio(u32),
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceeded::OutputSize(max) => {
+                write!(f, "Decompressed size exceeds limit of {} bytes", max)
+            }
+            LimitExceeded::Ratio(max) => {
+                write!(f, "Decompression ratio exceeds limit of {}:1", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// What went wrong, in terms shared by every codec, so generic code can tell
+/// failures apart without parsing messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input is not valid compressed data.
+    CorruptInput,
+    /// The data decoded, but a checksum or length check over it failed.
+    ChecksumMismatch,
+    /// The input ended in the middle of a stream.
+    UnexpectedEof,
+    /// An option is out of range, or doesn't fit the format or the data (a
+    /// missing or wrong dictionary, say).
+    InvalidOption,
+    /// A stream was used after it was finished.
+    UsedAfterFinish,
+    /// Decompressed output ran past a `DecompressionOptions` limit.
+    LimitExceeded,
+    /// An io error of no more specific kind.
+    Io,
+}
+
+impl ErrorKind {
+    /// The kind of an io error. Decoders built on `Read`/`Write` report bad
+    /// and truncated input this way.
+    pub fn from_io(e: &std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => ErrorKind::UnexpectedEof,
+            std::io::ErrorKind::InvalidData => ErrorKind::CorruptInput,
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
+/// A codec failure that is neither an io error nor a tripped limit: its kind
+/// and a description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl CodecError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+/// An error that can say which `ErrorKind` it is. Every codec's error type
+/// implements it.
+pub trait HasErrorKind: std::error::Error {
+    fn kind(&self) -> ErrorKind;
+}
+
+impl HasErrorKind for CodecError {
+    fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+/// Running byte counts of a decompression stream, checked against its
+/// `DecompressionOptions` each time output is produced.
+#[derive(Debug, Clone)]
+pub struct DecompressionLimits {
+    options: DecompressionOptions,
+    total_in: u64,
+    total_out: u64,
+}
+
+impl DecompressionLimits {
+    /// Output below this size never trips the ratio limit, so a short but
+    /// highly compressible stream is not rejected on its first few bytes.
+    pub const RATIO_GRACE_BYTES: u64 = 64 * 1024;
+
+    pub fn new(options: DecompressionOptions) -> Self {
+        Self {
+            options,
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+
+    /// Record `len` bytes of compressed input.
+    pub fn add_input(&mut self, len: usize) {
+        self.total_in += len as u64;
+    }
+
+    /// Most output that can still be produced from the input recorded so far
+    /// without tripping a limit, or `None` when there are no limits.
+    pub fn remaining_output(&self) -> Option<u64> {
+        let by_size = self.options.max_output_size;
+        let by_ratio = self.options.max_ratio.map(|max| {
+            self.total_in.saturating_mul(max as u64).max(Self::RATIO_GRACE_BYTES)
+        });
+        let allowed = match (by_size, by_ratio) {
+            (Some(a), Some(b)) => a.min(b),
+            (a, b) => a.or(b)?,
+        };
+        Some(allowed.saturating_sub(self.total_out))
+    }
+
+    /// Record `len` bytes of decompressed output, failing if that takes the
+    /// stream past either limit.
+    pub fn add_output(&mut self, len: usize) -> Result<(), LimitExceeded> {
+        self.total_out += len as u64;
+        self.check(self.total_out)
+    }
+
+    /// Fail if `len` more bytes of output would take the stream past either
+    /// limit, without recording them. For decoders that must hold output back
+    /// (a window of history, say) before it can be counted.
+    pub fn check_output(&self, len: u64) -> Result<(), LimitExceeded> {
+        self.check(self.total_out.saturating_add(len))
+    }
+
+    fn check(&self, total_out: u64) -> Result<(), LimitExceeded> {
+        if let Some(max) = self.options.max_output_size {
+            if total_out > max {
+                return Err(LimitExceeded::OutputSize(max));
+            }
+        }
+        if let Some(max) = self.options.max_ratio {
+            if total_out > Self::RATIO_GRACE_BYTES
+                && total_out > self.total_in.saturating_mul(max as u64)
+            {
+                return Err(LimitExceeded::Ratio(max));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Core trait for compression codecs.
 pub trait Compressor {
     /// Error type for this codec.
-    type Error: std::error::Error + Send + Sync + 'static;
+    type Error: HasErrorKind + Send + Sync + 'static;
 
     /// Create a new compressor with the given options.
     fn new(options: CompressionOptions) -> Result<Self, Self::Error>
@@ -69,3 +254,37 @@ pub trait Compressor {
 
 
 
+
+/// Core trait for decompression codecs.
+pub trait Decompressor {
+    /// Error type for this codec.
+    type Error: HasErrorKind + Send + Sync + 'static;
+
+    /// Create a new decompressor that enforces the given limits.
+    fn new(options: DecompressionOptions) -> Result<Self, Self::Error>
+    where
+        Self: Sized;
+
+    /// Decompress a chunk of input.
+    ///
+    /// - `input`: next slice of compressed data
+    /// - `finish`: whether this chunk completes the compressed stream
+    ///
+    /// Returns a buffer containing *all* produced output bytes for this call.
+    /// Passing `finish` on a stream that is not complete is an error, and so is
+    /// output that runs past the limits given to `new`; decoding stops as soon
+    /// as a limit is hit.
+    fn decompress_chunk(
+        &mut self,
+        input: &[u8],
+        finish: bool,
+    ) -> Result<Vec<u8>, Self::Error>;
+
+    /// Convenience non-streaming decompression: one-shot.
+    fn decompress_all(
+        input: &[u8],
+        options: DecompressionOptions,
+    ) -> Result<Vec<u8>, Self::Error>
+    where
+        Self: Sized;
+}
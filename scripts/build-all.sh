@@ -33,5 +33,6 @@ build_codec() {
 build_codec "gzip"
 build_codec "brotli"
 build_codec "lz4"
+build_codec "zstd"
 
 echo "All builds complete!"
@@ -1,6 +1,6 @@
 // Test streaming compression for all codecs
 import { init as initGzip, compress as compressGzip, decompress as decompressGzip, StreamingCompressor as GzipStreaming } from '../js/gzip/dist/node.js';
-import { init as initBrotli, compress as compressBrotli, decompress as decompressBrotli, StreamingCompressor as BrotliStreaming } from '../js/brotli/dist/node.js';
+import { init as initBrotli, compress as compressBrotli, decompress as decompressBrotli, StreamingCompressor as BrotliStreaming, StreamingDecompressor as BrotliStreamingDecompressor } from '../js/brotli/dist/node.js';
 import { init as initLz4, compress as compressLz4, decompress as decompressLz4, StreamingCompressor as Lz4Streaming } from '../js/lz4/dist/node.js';
 
 async function testGzip() {
@@ -35,24 +35,44 @@ async function testBrotli() {
   console.log('\n=== Testing Brotli Streaming ===');
   await initBrotli();
   
-  // Note: Brotli streaming compression has a limitation - it buffers all data
-  // until finish is called. For true streaming with output on each chunk,
-  // use Gzip or LZ4.
-  
   const compressor = new BrotliStreaming({ level: 6 });
   
   const chunk1 = new TextEncoder().encode('Hello ');
   const chunk2 = new TextEncoder().encode('World!');
   
+  // Small inputs usually stay inside the encoder until finish; larger streams
+  // emit output on each chunk as meta-blocks complete.
   const compressed1 = await compressor.compressChunk(chunk1, false);
-  console.log(`Chunk 1: ${compressed1.length} bytes (Brotli buffers until finish)`);
+  console.log(`Chunk 1: ${compressed1.length} bytes`);
   
   const compressed2 = await compressor.compressChunk(chunk2, true);
   console.log(`Chunk 2 (finish): ${compressed2.length} bytes`);
   
-  // Note: Brotli streaming is primarily useful for feeding data incrementally
-  // and getting a single compressed output. The compress_all one-shot is more reliable.
-  console.log(`✅ Brotli Streaming: API works (use one-shot for production)`);
+  // Combine compressed chunks
+  const combined = new Uint8Array(compressed1.length + compressed2.length);
+  combined.set(compressed1, 0);
+  combined.set(compressed2, compressed1.length);
+  
+  // Decompress
+  const decompressed = await decompressBrotli(combined);
+  const text = new TextDecoder().decode(decompressed);
+  
+  console.log(`Decompressed: "${text}"`);
+  console.log(`✅ Brotli Streaming: ${text === 'Hello World!' ? 'PASS' : 'FAIL'}`);
+  
+  // Streaming decompression, one byte at a time
+  const decompressor = new BrotliStreamingDecompressor();
+  let streamed = '';
+  for (let i = 0; i < combined.length; i++) {
+    const out = await decompressor.decompressChunk(combined.subarray(i, i + 1), false);
+    streamed += new TextDecoder().decode(out);
+  }
+  while (true) {
+    const out = await decompressor.decompressChunk(new Uint8Array(0), true);
+    if (!out.length) break;
+    streamed += new TextDecoder().decode(out);
+  }
+  console.log(`✅ Brotli Streaming Decompression: ${streamed === 'Hello World!' ? 'PASS' : 'FAIL'}`);
 }
 
 async function testLz4() {